@@ -768,8 +768,40 @@ public class MyTester
         Console.WriteLine("OK");
     }
 
-    public static void Main()
+    // Keys of rust/vectors/non_ascii.tsv; ASCII-encoded, they end in lookup3 tails of every length
+    static readonly string[] NonAsciiKeys = {
+        "ä", "Ñu", "żółw", "Straße", "İstanbul", "ﬁle", "München-01", "Ærøskøbing", "東京",
+        "日本語キー", "😀", "Ωmega-Ωmega-Ω", "tenant-ğüşiöç-0001", "Ελληνικά", "кириллица-ключ",
+        "ı-dotless", "ſ-long-s", "ǆemal", "Åbc", "Gödel-Nr1", "Ærø-123456", "ÆRØ-1234567890123",
+        "Öl-Fass", "Größe-12345", "naïve-résumé",
+    };
+
+    // `dotnet run -- vectors > ../rust/vectors/non_ascii.tsv`
+    static void PrintNonAsciiVectors()
     {
+        Console.OutputEncoding = new UTF8Encoding(encoderShouldEmitUTF8Identifier: false);
+        Console.Out.NewLine = "\n";
+        Console.WriteLine("# .NET parity vectors for non-ASCII keys: key<TAB>partition count<TAB>expected partition id<TAB>encoding");
+        Console.WriteLine("# Printed by `dotnet run -- vectors` in dotnet/ from DeterminePartitionId, which upper-cases with the");
+        Console.WriteLine("# invariant culture and hashes ASCIIEncoding bytes, so every key is an `ascii` vector.");
+        Console.WriteLine();
+        foreach (var key in NonAsciiKeys)
+        {
+            foreach (var partitionCount in new short[] { 32, short.MaxValue })
+            {
+                Console.WriteLine($"{key}\t{partitionCount}\t{key.DeterminePartitionId(partitionCount)}\tascii");
+            }
+        }
+    }
+
+    public static void Main(string[] args)
+    {
+        if (args is ["vectors"])
+        {
+            PrintNonAsciiVectors();
+            return;
+        }
+
         BruteForce();
         TestCompliance();
         foreach (var x in Enumerable.Range(0, 32).Select(desiredPartitionId => BruteForceGetFor(desiredPartitionId: (short)desiredPartitionId, partitionCount: 32)))
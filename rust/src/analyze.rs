@@ -0,0 +1,419 @@
+use clap::Args;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+
+use crate::hll::{self, HyperLogLog};
+use crate::partitioner::Scheme;
+use crate::print_json;
+use crate::records::RecordFields;
+use crate::topk::{KeyCount, SpaceSaving};
+
+#[derive(Args, Debug)]
+pub struct AnalyzeArgs {
+    /// Key files with one record per line (reads stdin when neither files nor sketches are given)
+    files: Vec<PathBuf>,
+
+    /// Number of partitions
+    #[clap(short = 'c', long, value_parser = clap::value_parser!(i16).range(1..))]
+    partition_count: i16,
+
+    /// Partitioning scheme that places the keys
+    #[clap(long, value_enum, default_value_t)]
+    scheme: Scheme,
+
+    #[command(flatten)]
+    fields: RecordFields,
+
+    /// Number of heaviest keys to report per partition
+    #[clap(long, default_value_t = 5)]
+    top: usize,
+
+    /// Counters kept per partition for the heavy-hitter summaries; more counters, tighter counts
+    #[clap(long, default_value_t = 256, value_parser = clap::value_parser!(u64).range(1..))]
+    top_capacity: u64,
+
+    /// HyperLogLog precision for the distinct-key estimates; 2^p registers per partition,
+    /// about 1.04/sqrt(2^p) relative error
+    #[clap(long, default_value_t = hll::DEFAULT_PRECISION, value_parser = clap::value_parser!(u8).range(4..=16))]
+    hll_precision: u8,
+
+    /// Merge a sketch saved by an earlier run, e.g. of yesterday's keys (repeatable)
+    #[clap(long)]
+    merge: Vec<PathBuf>,
+
+    /// Save the merged sketch, so later runs can combine it without reading the keys again
+    #[clap(long)]
+    save_sketch: Option<PathBuf>,
+
+    /// Print JSON instead of a table
+    #[clap(long)]
+    json: bool,
+}
+
+/// Per-partition counts, heavy-hitter summaries and distinct-key sketches of a key stream.
+/// Two analyses with the same scheme and partition count merge into the analysis of both streams.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Analysis {
+    #[serde(default)]
+    pub scheme: Scheme,
+    pub partition_count: i16,
+    pub skipped_lines: u64,
+    /// Summed record weights per partition, i.e. messages when the records are not weighted
+    pub messages: Vec<f64>,
+    top_keys: Vec<SpaceSaving>,
+    distinct_keys: Vec<HyperLogLog>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Report {
+    pub scheme: Scheme,
+    pub partition_count: i16,
+    /// Summed record weights
+    pub messages: f64,
+    pub skipped_lines: u64,
+    /// Estimated distinct keys; a key always maps to one partition, so this is the sum over partitions
+    pub distinct_keys: u64,
+    /// Messages of the busiest partition divided by the mean
+    pub max_to_mean: f64,
+    /// Standard deviation of the partition messages divided by the mean
+    pub variation: f64,
+    pub partitions: Vec<PartitionReport>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PartitionReport {
+    pub partition_id: u16,
+    pub messages: f64,
+    pub share: f64,
+    pub distinct_keys: u64,
+    pub top_keys: Vec<KeyCount>,
+}
+
+impl Analysis {
+    pub fn new(
+        scheme: Scheme,
+        partition_count: i16,
+        top_capacity: usize,
+        hll_precision: u8,
+    ) -> Self {
+        Analysis {
+            scheme,
+            partition_count,
+            skipped_lines: 0,
+            messages: vec![0.0; partition_count as usize],
+            top_keys: vec![SpaceSaving::new(top_capacity); partition_count as usize],
+            distinct_keys: vec![HyperLogLog::new(hll_precision); partition_count as usize],
+        }
+    }
+
+    pub fn add(&mut self, partition_id: u16, key: &str, weight: f64) {
+        self.messages[partition_id as usize] += weight;
+        self.top_keys[partition_id as usize].add(key, weight);
+        self.distinct_keys[partition_id as usize].add(key);
+    }
+
+    pub fn merge(&mut self, other: &Analysis) -> Result<(), String> {
+        if other.scheme != self.scheme {
+            return Err(format!(
+                "cannot merge an analysis of the {} scheme into one of the {} scheme",
+                other.scheme.name(),
+                self.scheme.name()
+            ));
+        }
+        if other.partition_count != self.partition_count {
+            return Err(format!(
+                "cannot merge an analysis of {} partitions into one of {}",
+                other.partition_count, self.partition_count
+            ));
+        }
+        self.skipped_lines += other.skipped_lines;
+        for partition in 0..self.messages.len() {
+            self.messages[partition] += other.messages[partition];
+            self.top_keys[partition].merge(&other.top_keys[partition]);
+            self.distinct_keys[partition].merge(&other.distinct_keys[partition])?;
+        }
+        Ok(())
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| format!("cannot read {}: {e}", path.display()))?;
+        let mut analysis: Analysis = serde_json::from_str(&text)
+            .map_err(|e| format!("{} is not a sketch file: {e}", path.display()))?;
+        if analysis.messages.len() != analysis.partition_count as usize
+            || analysis.top_keys.len() != analysis.partition_count as usize
+            || analysis.distinct_keys.len() != analysis.partition_count as usize
+        {
+            return Err(format!(
+                "{} does not have one entry per partition",
+                path.display()
+            ));
+        }
+        for sketch in &analysis.distinct_keys {
+            sketch
+                .validate()
+                .map_err(|e| format!("{}: {e}", path.display()))?;
+        }
+        analysis.top_keys.iter_mut().for_each(SpaceSaving::reindex);
+        Ok(analysis)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let json = serde_json::to_string(self).map_err(|e| e.to_string())?;
+        std::fs::write(path, json).map_err(|e| format!("cannot write {}: {e}", path.display()))
+    }
+
+    pub fn report(&self, top: usize) -> Report {
+        let total: f64 = self.messages.iter().sum();
+        let max = self.messages.iter().copied().fold(0.0, f64::max);
+        let mean = total / self.messages.len() as f64;
+        let distinct: Vec<u64> = self
+            .distinct_keys
+            .iter()
+            .map(HyperLogLog::estimate)
+            .collect();
+        Report {
+            scheme: self.scheme,
+            partition_count: self.partition_count,
+            messages: total,
+            skipped_lines: self.skipped_lines,
+            distinct_keys: distinct.iter().sum(),
+            max_to_mean: if total == 0.0 { 0.0 } else { max / mean },
+            variation: if total == 0.0 {
+                0.0
+            } else {
+                let variance = self
+                    .messages
+                    .iter()
+                    .map(|messages| (messages - mean).powi(2))
+                    .sum::<f64>()
+                    / self.messages.len() as f64;
+                variance.sqrt() / mean
+            },
+            partitions: self
+                .messages
+                .iter()
+                .zip(&self.top_keys)
+                .enumerate()
+                .map(|(partition, (messages, top_keys))| PartitionReport {
+                    partition_id: partition as u16,
+                    messages: *messages,
+                    share: if total == 0.0 { 0.0 } else { messages / total },
+                    distinct_keys: distinct[partition],
+                    top_keys: top_keys.top(top),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Analyzes the lines of `reader`; lines without a key or a valid weight are skipped.
+pub fn analyze_lines(
+    reader: impl BufRead,
+    scheme: Scheme,
+    partition_count: i16,
+    fields: &RecordFields,
+    top_capacity: usize,
+    hll_precision: u8,
+) -> Result<Analysis, String> {
+    let partitioner = scheme.partitioner(partition_count);
+    let mut analysis = Analysis::new(scheme, partition_count, top_capacity, hll_precision);
+    for line in reader.lines() {
+        let line = line.map_err(|e| e.to_string())?;
+        match fields.parse(&line) {
+            Some((key, weight)) => {
+                analysis.add(partitioner.partition_of(&key), &key, weight);
+            }
+            None => analysis.skipped_lines += 1,
+        }
+    }
+    Ok(analysis)
+}
+
+pub fn run(args: &AnalyzeArgs) -> Result<(), String> {
+    args.scheme.check_partition_count(args.partition_count)?;
+    let top_capacity = args.top_capacity as usize;
+    let analyze_file = |path: &PathBuf| {
+        let file = std::fs::File::open(path)
+            .map_err(|e| format!("cannot open {}: {e}", path.display()))?;
+        analyze_lines(
+            BufReader::new(file),
+            args.scheme,
+            args.partition_count,
+            &args.fields,
+            top_capacity,
+            args.hll_precision,
+        )
+        .map_err(|e| format!("cannot read {}: {e}", path.display()))
+    };
+
+    // Every file gets its own sketch; the sketches are merged afterwards.
+    let mut parts: Vec<Analysis> = args
+        .files
+        .par_iter()
+        .map(analyze_file)
+        .collect::<Result<_, _>>()?;
+    for path in &args.merge {
+        parts.push(Analysis::load(path)?);
+    }
+    if args.files.is_empty() && args.merge.is_empty() {
+        parts.push(analyze_lines(
+            std::io::stdin().lock(),
+            args.scheme,
+            args.partition_count,
+            &args.fields,
+            top_capacity,
+            args.hll_precision,
+        )?);
+    }
+
+    let mut analysis = Analysis::new(
+        args.scheme,
+        args.partition_count,
+        top_capacity,
+        args.hll_precision,
+    );
+    for part in &parts {
+        analysis.merge(part)?;
+    }
+    if let Some(path) = &args.save_sketch {
+        analysis.save(path)?;
+    }
+
+    let report = analysis.report(args.top);
+    if args.json {
+        return print_json(&report);
+    }
+    let unit = match args.fields.weight_field {
+        Some(_) => "weight",
+        None => "messages",
+    };
+    println!(
+        "{unit}: {}, distinct keys: ~{}, lines skipped: {}, busiest partition at {:.2}x the mean",
+        format_weight(report.messages),
+        report.distinct_keys,
+        report.skipped_lines,
+        report.max_to_mean
+    );
+    println!(
+        "{:>12} {:>10} {:>10} {:>7}  top keys",
+        "partition_id", unit, "distinct", "share"
+    );
+    for partition in &report.partitions {
+        let top_keys: Vec<String> = partition
+            .top_keys
+            .iter()
+            .map(|entry| format!("{} ({})", entry.key, format_count(entry)))
+            .collect();
+        println!(
+            "{:>12} {:>10} {:>10} {:>6.2}%  {}",
+            partition.partition_id,
+            format_weight(partition.messages),
+            partition.distinct_keys,
+            100.0 * partition.share,
+            top_keys.join(", ")
+        );
+    }
+    Ok(())
+}
+
+/// A heavy-hitter count, with its uncertainty when the summary had to evict keys.
+pub fn format_count(entry: &KeyCount) -> String {
+    if entry.error == 0.0 {
+        format_weight(entry.count)
+    } else {
+        format!(
+            "{}±{}",
+            format_weight(entry.count),
+            format_weight(entry.error)
+        )
+    }
+}
+
+/// A summed weight, without decimals when the weights were whole numbers.
+pub fn format_weight(weight: f64) -> String {
+    if weight.fract() == 0.0 {
+        format!("{weight:.0}")
+    } else {
+        format!("{weight:.2}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::get_partition_id;
+    use crate::records::Field;
+
+    #[test]
+    fn test_merged_files_match_one_pass() -> Result<(), String> {
+        let first = "a\nb\na\n\nc\n";
+        let second = "a\nd\nb\n";
+        let whole = format!("{first}{second}");
+        let fields = RecordFields::default();
+
+        let mut merged = analyze_lines(first.as_bytes(), Scheme::ServiceBus, 4, &fields, 16, 8)?;
+        merged.merge(&analyze_lines(
+            second.as_bytes(),
+            Scheme::ServiceBus,
+            4,
+            &fields,
+            16,
+            8,
+        )?)?;
+        let single = analyze_lines(whole.as_bytes(), Scheme::ServiceBus, 4, &fields, 16, 8)?;
+
+        assert_eq!(merged.messages, single.messages);
+        assert_eq!(merged.skipped_lines, 1);
+        let report = merged.report(1);
+        let hot = &report.partitions[get_partition_id(4, "a") as usize];
+        assert_eq!(
+            (hot.top_keys[0].key.as_str(), hot.top_keys[0].count),
+            ("a", 3.0)
+        );
+        assert_eq!(report.distinct_keys, 4);
+
+        assert!(merged
+            .merge(&Analysis::new(Scheme::ServiceBus, 8, 16, 8))
+            .is_err());
+        assert!(merged
+            .merge(&Analysis::new(Scheme::ServiceBus, 4, 16, 10))
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_weighted_table_matches_raw_events() -> Result<(), String> {
+        let raw = analyze_lines(
+            "a\na\na\nb\n".as_bytes(),
+            Scheme::ServiceBus,
+            4,
+            &RecordFields::default(),
+            16,
+            8,
+        )?;
+        let fields = RecordFields {
+            key_field: Field::Column(1),
+            weight_field: Some(Field::Column(2)),
+        };
+        let table = analyze_lines(
+            "a\t3\nb\t1\nc\tmany\n".as_bytes(),
+            Scheme::ServiceBus,
+            4,
+            &fields,
+            16,
+            8,
+        )?;
+
+        assert_eq!(table.messages, raw.messages);
+        assert_eq!(table.skipped_lines, 1);
+        let (raw, table) = (raw.report(2), table.report(2));
+        assert_eq!(table.max_to_mean, raw.max_to_mean);
+        for (raw, table) in raw.partitions.iter().zip(&table.partitions) {
+            assert_eq!(table.top_keys, raw.top_keys);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,230 @@
+use clap::Args;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::io::{BufRead, BufReader};
+use std::path::PathBuf;
+
+use crate::ranges::range_table;
+use crate::{print_json, to_logical, to_partition_id};
+
+/// Maximum batch size of a Service Bus standard namespace (256 KB).
+const STANDARD_MAX_BATCH_BYTES: usize = 256 * 1024;
+
+#[derive(Args, Debug)]
+pub struct BatchArgs {
+    /// Number of partitions
+    #[clap(short = 'c', long, value_parser = clap::value_parser!(i16).range(1..))]
+    partition_count: i16,
+
+    /// Maximum size of a single batch in bytes (256 KB standard, 1 MB or more premium)
+    #[clap(long, default_value_t = STANDARD_MAX_BATCH_BYTES)]
+    max_bytes: usize,
+
+    /// Maximum number of messages in a single batch
+    #[clap(long)]
+    max_messages: Option<usize>,
+
+    /// File with one `key<TAB>size` line per message (reads stdin when omitted)
+    #[clap(short = 'i', long)]
+    input: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Message {
+    pub key: String,
+    pub size: usize,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct BatchLimits {
+    pub max_bytes: usize,
+    pub max_messages: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PlannedMessage {
+    /// Position of the message in the input
+    pub index: usize,
+    pub key: String,
+    pub size: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Batch {
+    pub partition_id: u16,
+    pub bytes: usize,
+    pub messages: Vec<PlannedMessage>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BatchPlan {
+    pub partition_count: i16,
+    pub max_bytes: usize,
+    pub max_messages: Option<usize>,
+    pub batches: Vec<Batch>,
+}
+
+impl BatchArgs {
+    /// The partition counts whose range tables the run looks up.
+    pub fn partition_counts(&self) -> Vec<i16> {
+        vec![self.partition_count]
+    }
+}
+
+pub fn run(args: &BatchArgs) -> Result<(), String> {
+    let messages = match &args.input {
+        Some(path) => {
+            let file = std::fs::File::open(path)
+                .map_err(|e| format!("cannot open {}: {e}", path.display()))?;
+            read_messages(BufReader::new(file))?
+        }
+        None => read_messages(std::io::stdin().lock())?,
+    };
+
+    let limits = BatchLimits {
+        max_bytes: args.max_bytes,
+        max_messages: args.max_messages,
+    };
+    let plan = plan_batches(&messages, args.partition_count, &limits)?;
+
+    print_json(&plan)
+}
+
+/// Reads `key<TAB>size` lines, skipping blank lines.
+pub fn read_messages(reader: impl BufRead) -> Result<Vec<Message>, String> {
+    let mut messages = Vec::new();
+    for (number, line) in reader.lines().enumerate() {
+        let line = line.map_err(|e| e.to_string())?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let (key, size) = line
+            .rsplit_once('\t')
+            .ok_or_else(|| format!("line {}: expected `key<TAB>size`", number + 1))?;
+        let size = size
+            .trim()
+            .parse()
+            .map_err(|_| format!("line {}: invalid size {size:?}", number + 1))?;
+        messages.push(Message {
+            key: key.to_string(),
+            size,
+        });
+    }
+    Ok(messages)
+}
+
+/// Groups messages by partition, keeping the input order within each partition,
+/// and splits every group into batches that stay within `limits`.
+pub fn plan_batches(
+    messages: &[Message],
+    partition_count: i16,
+    limits: &BatchLimits,
+) -> Result<BatchPlan, String> {
+    if limits.max_messages == Some(0) {
+        return Err("maximum message count must be at least 1".to_string());
+    }
+
+    let ranges = range_table(partition_count);
+    let mut by_partition: BTreeMap<u16, Vec<PlannedMessage>> = BTreeMap::new();
+    for (index, message) in messages.iter().enumerate() {
+        if message.size > limits.max_bytes {
+            return Err(format!(
+                "message {index} ({} bytes) exceeds the maximum batch size of {} bytes",
+                message.size, limits.max_bytes
+            ));
+        }
+        let partition_id = to_partition_id(&ranges, to_logical(message.key.clone()));
+        by_partition
+            .entry(partition_id)
+            .or_default()
+            .push(PlannedMessage {
+                index,
+                key: message.key.clone(),
+                size: message.size,
+            });
+    }
+
+    let mut batches = Vec::new();
+    for (partition_id, partition_messages) in by_partition {
+        let mut current = Batch {
+            partition_id,
+            bytes: 0,
+            messages: Vec::new(),
+        };
+        for message in partition_messages {
+            let full_by_bytes = current.bytes + message.size > limits.max_bytes;
+            let full_by_count = limits
+                .max_messages
+                .is_some_and(|max| current.messages.len() >= max);
+            if !current.messages.is_empty() && (full_by_bytes || full_by_count) {
+                batches.push(std::mem::replace(
+                    &mut current,
+                    Batch {
+                        partition_id,
+                        bytes: 0,
+                        messages: Vec::new(),
+                    },
+                ));
+            }
+            current.bytes += message.size;
+            current.messages.push(message);
+        }
+        batches.push(current);
+    }
+
+    Ok(BatchPlan {
+        partition_count,
+        max_bytes: limits.max_bytes,
+        max_messages: limits.max_messages,
+        batches,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(key: &str, size: usize) -> Message {
+        Message {
+            key: key.to_string(),
+            size,
+        }
+    }
+
+    #[test]
+    fn test_batches_respect_limits_and_order() -> Result<(), String> {
+        // Both keys land in partition 13 of 32.
+        let a = "00000000-1313-3232-80C6-DEADDEADBEEF";
+        let b = "00000000-1313-3232-8164-DEADDEADBEEF";
+        let messages = vec![
+            message(a, 60),
+            message(b, 50),
+            message(a, 10),
+            message(b, 30),
+        ];
+        let limits = BatchLimits {
+            max_bytes: 100,
+            max_messages: Some(2),
+        };
+
+        let plan = plan_batches(&messages, 32, &limits)?;
+
+        assert!(plan.batches.iter().all(|batch| batch.partition_id == 13));
+        let indices: Vec<Vec<usize>> = plan
+            .batches
+            .iter()
+            .map(|batch| batch.messages.iter().map(|m| m.index).collect())
+            .collect();
+        assert_eq!(indices, vec![vec![0], vec![1, 2], vec![3]]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_oversized_message_is_rejected() {
+        let limits = BatchLimits {
+            max_bytes: 100,
+            max_messages: None,
+        };
+        assert!(plan_batches(&[message("key", 101)], 4, &limits).is_err());
+    }
+}
@@ -0,0 +1,425 @@
+//! A partitioned queue that stands in for the broker in offline integration tests.
+//!
+//! Messages are routed like the real entity routes them: the partition key (or, without one,
+//! the session id) goes through the same partitioner as every other mode, and messages
+//! without either are spread round robin. Every partition is a FIFO queue; session messages
+//! are only handed out to the one receiver that holds the session's lock.
+//!
+//! `partition_id broker` serves the queue on a TCP port with one command per line and one
+//! JSON document per response line:
+//!
+//! ```text
+//! SEND <partition-key|-> <session-id|-> <body>   {"partition_id":3,"sequence_number":1}
+//! PEEK <partition> [max]                         [{"sequence_number":1,...}]
+//! RECEIVE <partition> [max]                      messages without a session, removed
+//! ACCEPT <session-id>                            {"session_id":"s1","partition_id":3}
+//! RECEIVE-SESSION <session-id> [max]             the session's messages, removed
+//! RELEASE <session-id>                           {"released":"s1"}
+//! STATS                                          [{"partition_id":0,"messages":2,...}]
+//! ```
+//!
+//! Failures answer `{"error":"..."}`. A connection's session locks end with the connection.
+
+use clap::Args;
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::sync::{Arc, Mutex};
+
+use crate::partitioner::{Partitioner, Scheme};
+
+#[derive(Args, Debug)]
+pub struct BrokerArgs {
+    /// Number of partitions
+    #[clap(short = 'c', long, value_parser = clap::value_parser!(i16).range(1..))]
+    partition_count: i16,
+
+    /// Partitioning scheme that places the messages
+    #[clap(long, value_enum, default_value_t)]
+    scheme: Scheme,
+
+    /// Address to serve the line protocol on
+    #[clap(long, default_value = "127.0.0.1:5300")]
+    listen: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Message {
+    /// Position in the whole broker, increasing in send order
+    pub sequence_number: u64,
+    pub partition_id: u16,
+    pub partition_key: Option<String>,
+    pub session_id: Option<String>,
+    pub body: String,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+pub struct PartitionStats {
+    pub partition_id: u16,
+    pub messages: usize,
+    pub session_messages: usize,
+}
+
+/// Identifies a receiver, such as one connection, for session locks.
+pub type ReceiverId = u64;
+
+pub struct Broker {
+    partitioner: Box<dyn Partitioner>,
+    partitions: Vec<VecDeque<Message>>,
+    next_sequence_number: u64,
+    next_round_robin: usize,
+    /// Session id to the receiver that holds its lock
+    session_locks: HashMap<String, ReceiverId>,
+}
+
+impl Broker {
+    pub fn new(scheme: Scheme, partition_count: i16) -> Self {
+        Broker {
+            partitioner: scheme.partitioner(partition_count),
+            partitions: vec![VecDeque::new(); partition_count as usize],
+            next_sequence_number: 1,
+            next_round_robin: 0,
+            session_locks: HashMap::new(),
+        }
+    }
+
+    /// The partition of a session, the same one its messages are sent to.
+    pub fn session_partition(&self, session_id: &str) -> u16 {
+        self.partitioner.partition_of(session_id)
+    }
+
+    /// Enqueues a message at the end of its partition. As on Service Bus, a message with both
+    /// a partition key and a session id needs them to be equal.
+    pub fn send(
+        &mut self,
+        partition_key: Option<&str>,
+        session_id: Option<&str>,
+        body: &str,
+    ) -> Result<&Message, String> {
+        let partition_id = match (partition_key, session_id) {
+            (Some(key), Some(session)) if key != session => {
+                return Err(format!(
+                    "partition key {key:?} differs from session id {session:?}"
+                ))
+            }
+            (Some(key), _) | (None, Some(key)) => self.partitioner.partition_of(key),
+            (None, None) => {
+                let partition_id = self.next_round_robin;
+                self.next_round_robin = (partition_id + 1) % self.partitions.len();
+                partition_id as u16
+            }
+        };
+        let message = Message {
+            sequence_number: self.next_sequence_number,
+            partition_id,
+            partition_key: partition_key.map(str::to_string),
+            session_id: session_id.map(str::to_string),
+            body: body.to_string(),
+        };
+        self.next_sequence_number += 1;
+        let queue = &mut self.partitions[partition_id as usize];
+        queue.push_back(message);
+        Ok(queue.back().unwrap())
+    }
+
+    fn queue(&self, partition_id: u16) -> Result<&VecDeque<Message>, String> {
+        self.partitions.get(partition_id as usize).ok_or_else(|| {
+            format!(
+                "partition {partition_id} does not exist with {} partitions",
+                self.partitions.len()
+            )
+        })
+    }
+
+    /// The first `max` messages of a partition, sessions included, without removing them.
+    pub fn peek(&self, partition_id: u16, max: usize) -> Result<Vec<Message>, String> {
+        Ok(self
+            .queue(partition_id)?
+            .iter()
+            .take(max)
+            .cloned()
+            .collect())
+    }
+
+    /// Removes and returns up to `max` messages without a session, oldest first.
+    pub fn receive(&mut self, partition_id: u16, max: usize) -> Result<Vec<Message>, String> {
+        self.queue(partition_id)?;
+        Ok(self.take(partition_id, max, |message| message.session_id.is_none()))
+    }
+
+    /// Locks a session for `receiver`; fails while another receiver holds it.
+    pub fn accept_session(
+        &mut self,
+        session_id: &str,
+        receiver: ReceiverId,
+    ) -> Result<u16, String> {
+        match self.session_locks.get(session_id) {
+            Some(holder) if *holder != receiver => Err(format!(
+                "session {session_id:?} is locked by another receiver"
+            )),
+            _ => {
+                self.session_locks.insert(session_id.to_string(), receiver);
+                Ok(self.session_partition(session_id))
+            }
+        }
+    }
+
+    /// Removes and returns up to `max` messages of a session that `receiver` holds.
+    pub fn receive_session(
+        &mut self,
+        session_id: &str,
+        receiver: ReceiverId,
+        max: usize,
+    ) -> Result<Vec<Message>, String> {
+        if self.session_locks.get(session_id) != Some(&receiver) {
+            return Err(format!(
+                "session {session_id:?} is not accepted by this receiver"
+            ));
+        }
+        let partition_id = self.session_partition(session_id);
+        Ok(self.take(partition_id, max, |message| {
+            message.session_id.as_deref() == Some(session_id)
+        }))
+    }
+
+    pub fn release_session(
+        &mut self,
+        session_id: &str,
+        receiver: ReceiverId,
+    ) -> Result<(), String> {
+        match self.session_locks.get(session_id) {
+            Some(holder) if *holder == receiver => {
+                self.session_locks.remove(session_id);
+                Ok(())
+            }
+            _ => Err(format!(
+                "session {session_id:?} is not accepted by this receiver"
+            )),
+        }
+    }
+
+    /// Drops every session lock of a receiver that went away.
+    pub fn release_receiver(&mut self, receiver: ReceiverId) {
+        self.session_locks.retain(|_, holder| *holder != receiver);
+    }
+
+    pub fn stats(&self) -> Vec<PartitionStats> {
+        self.partitions
+            .iter()
+            .enumerate()
+            .map(|(partition_id, queue)| PartitionStats {
+                partition_id: partition_id as u16,
+                messages: queue.len(),
+                session_messages: queue
+                    .iter()
+                    .filter(|message| message.session_id.is_some())
+                    .count(),
+            })
+            .collect()
+    }
+
+    fn take(
+        &mut self,
+        partition_id: u16,
+        max: usize,
+        wanted: impl Fn(&Message) -> bool,
+    ) -> Vec<Message> {
+        let queue = &mut self.partitions[partition_id as usize];
+        let mut taken = Vec::new();
+        let mut index = 0;
+        while taken.len() < max && index < queue.len() {
+            if wanted(&queue[index]) {
+                taken.extend(queue.remove(index));
+            } else {
+                index += 1;
+            }
+        }
+        taken
+    }
+}
+
+/// Runs one protocol command for `receiver` and returns the JSON response line.
+pub fn handle_command(broker: &mut Broker, receiver: ReceiverId, line: &str) -> String {
+    let to_json = |value: Result<serde_json::Value, String>| match value {
+        Ok(value) => value.to_string(),
+        Err(message) => serde_json::json!({ "error": message }).to_string(),
+    };
+    to_json(execute(broker, receiver, line))
+}
+
+fn json<T: Serialize>(value: &T) -> Result<serde_json::Value, String> {
+    serde_json::to_value(value).map_err(|e| e.to_string())
+}
+
+fn execute(
+    broker: &mut Broker,
+    receiver: ReceiverId,
+    line: &str,
+) -> Result<serde_json::Value, String> {
+    let (command, rest) = line.trim().split_once(' ').unwrap_or((line.trim(), ""));
+    let mut arguments = rest.split_whitespace();
+    let max = |argument: Option<&str>| -> Result<usize, String> {
+        argument.map_or(Ok(1), |max| {
+            max.parse()
+                .map_err(|_| format!("{max:?} is not a message count"))
+        })
+    };
+    let partition = |argument: Option<&str>| -> Result<u16, String> {
+        let argument = argument.ok_or("missing partition id")?;
+        argument
+            .parse()
+            .map_err(|_| format!("{argument:?} is not a partition id"))
+    };
+    let optional = |argument: &str| (argument != "-").then(|| argument.to_string());
+
+    match command.to_ascii_uppercase().as_str() {
+        "SEND" => {
+            let mut parts = rest.trim_start().splitn(3, ' ');
+            let (Some(key), Some(session)) = (parts.next(), parts.next()) else {
+                return Err("usage: SEND <partition-key|-> <session-id|-> <body>".to_string());
+            };
+            let (key, session) = (optional(key), optional(session));
+            let message = broker.send(
+                key.as_deref(),
+                session.as_deref(),
+                parts.next().unwrap_or_default(),
+            )?;
+            Ok(serde_json::json!({
+                "partition_id": message.partition_id,
+                "sequence_number": message.sequence_number,
+            }))
+        }
+        "PEEK" => {
+            let partition_id = partition(arguments.next())?;
+            json(&broker.peek(partition_id, max(arguments.next())?)?)
+        }
+        "RECEIVE" => {
+            let partition_id = partition(arguments.next())?;
+            json(&broker.receive(partition_id, max(arguments.next())?)?)
+        }
+        "ACCEPT" => {
+            let session = arguments.next().ok_or("missing session id")?;
+            let partition_id = broker.accept_session(session, receiver)?;
+            Ok(serde_json::json!({ "session_id": session, "partition_id": partition_id }))
+        }
+        "RECEIVE-SESSION" => {
+            let session = arguments.next().ok_or("missing session id")?;
+            json(&broker.receive_session(session, receiver, max(arguments.next())?)?)
+        }
+        "RELEASE" => {
+            let session = arguments.next().ok_or("missing session id")?;
+            broker.release_session(session, receiver)?;
+            Ok(serde_json::json!({ "released": session }))
+        }
+        "STATS" => json(&broker.stats()),
+        other => Err(format!("unknown command {other:?}")),
+    }
+}
+
+pub fn run(args: &BrokerArgs) -> Result<(), String> {
+    args.scheme.check_partition_count(args.partition_count)?;
+    let listener = TcpListener::bind(&args.listen)
+        .map_err(|e| format!("cannot listen on {}: {e}", args.listen))?;
+    eprintln!(
+        "{} partitions ({}) on {}",
+        args.partition_count,
+        args.scheme.name(),
+        args.listen
+    );
+    let broker = Arc::new(Mutex::new(Broker::new(args.scheme, args.partition_count)));
+    for (receiver, stream) in listener.incoming().enumerate() {
+        let stream = stream.map_err(|e| e.to_string())?;
+        let broker = Arc::clone(&broker);
+        std::thread::spawn(move || {
+            let receiver = receiver as ReceiverId;
+            let mut writer = match stream.try_clone() {
+                Ok(writer) => writer,
+                Err(_) => return,
+            };
+            for line in BufReader::new(stream).lines() {
+                let Ok(line) = line else { break };
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let response = handle_command(&mut broker.lock().unwrap(), receiver, &line);
+                if writeln!(writer, "{response}").is_err() {
+                    break;
+                }
+            }
+            broker.lock().unwrap().release_receiver(receiver);
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::get_partition_id;
+
+    #[test]
+    fn test_routing_order_and_sessions() -> Result<(), String> {
+        let mut broker = Broker::new(Scheme::ServiceBus, 4);
+        for i in 0..40 {
+            let key = format!("order-{}", i % 5);
+            let message = broker.send(Some(&key), None, &i.to_string())?;
+            assert_eq!(message.partition_id, get_partition_id(4, &key));
+        }
+        // Every partition hands out its messages in send order.
+        for partition_id in 0..4 {
+            let received = broker.receive(partition_id, 100)?;
+            assert!(received
+                .windows(2)
+                .all(|pair| pair[0].sequence_number < pair[1].sequence_number));
+        }
+        assert!(broker.stats().iter().all(|stats| stats.messages == 0));
+
+        let keyless: Vec<u16> = (0..5)
+            .map(|_| Ok(broker.send(None, None, "")?.partition_id))
+            .collect::<Result<_, String>>()?;
+        assert_eq!(keyless, vec![0, 1, 2, 3, 0]);
+        assert!(broker.send(Some("a"), Some("b"), "").is_err());
+
+        let session_partition = broker.session_partition("cart-1");
+        assert_eq!(session_partition, get_partition_id(4, "cart-1"));
+        let waiting = broker.stats()[session_partition as usize].messages;
+        broker.send(None, Some("cart-1"), "first")?;
+        broker.send(Some("cart-1"), Some("cart-1"), "second")?;
+        assert_eq!(broker.peek(session_partition, 10)?.len(), waiting + 2);
+        // Plain receivers skip session messages.
+        assert_eq!(broker.receive(session_partition, 10)?.len(), waiting);
+        assert!(broker.receive_session("cart-1", 1, 10).is_err());
+        assert_eq!(broker.accept_session("cart-1", 1)?, session_partition);
+        assert!(broker.accept_session("cart-1", 2).is_err());
+        let bodies: Vec<String> = broker
+            .receive_session("cart-1", 1, 10)?
+            .into_iter()
+            .map(|message| message.body)
+            .collect();
+        assert_eq!(bodies, vec!["first", "second"]);
+        broker.release_receiver(1);
+        assert_eq!(broker.accept_session("cart-1", 2)?, session_partition);
+        Ok(())
+    }
+
+    #[test]
+    fn test_line_protocol() {
+        let mut broker = Broker::new(Scheme::ServiceBus, 2);
+        let partition_id = get_partition_id(2, "tenant-1");
+        assert_eq!(
+            handle_command(&mut broker, 7, "SEND tenant-1 - hello world"),
+            format!(r#"{{"partition_id":{partition_id},"sequence_number":1}}"#)
+        );
+        let peeked = handle_command(&mut broker, 7, &format!("PEEK {partition_id} 5"));
+        assert!(peeked.contains(r#""body":"hello world""#));
+        let received = handle_command(&mut broker, 7, &format!("receive {partition_id}"));
+        assert_eq!(received, peeked);
+        assert_eq!(
+            handle_command(&mut broker, 7, &format!("RECEIVE {partition_id}")),
+            "[]"
+        );
+        assert!(handle_command(&mut broker, 7, "PEEK 9").starts_with(r#"{"error""#));
+        assert!(handle_command(&mut broker, 7, "RELEASE s1").starts_with(r#"{"error""#));
+    }
+}
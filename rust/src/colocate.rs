@@ -0,0 +1,301 @@
+use clap::{Args, ValueEnum};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use crate::analyze::format_weight;
+use crate::cosmos::{load_ranges, CosmosRanges, HashVersion};
+use crate::partitioner::{Partitioner, Scheme};
+use crate::print_json;
+use crate::records::{read_key_files, RecordFields};
+use crate::stats::chi_square;
+
+#[derive(Args, Debug)]
+pub struct ColocateArgs {
+    /// Key files with one record per line (reads stdin when omitted)
+    files: Vec<PathBuf>,
+
+    /// Hops in the order the messages pass them: `scheme:count` such as `service-bus:32`,
+    /// or `cosmos:pkranges.json` for the physical partitions of a container (hash v2)
+    #[clap(long, value_delimiter = ',', required = true)]
+    hops: Vec<Hop>,
+
+    #[command(flatten)]
+    fields: RecordFields,
+
+    /// Also print the contingency matrix of every pair of neighbouring hops
+    #[clap(long)]
+    matrix: bool,
+
+    /// Print JSON instead of a table
+    #[clap(long)]
+    json: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Hop {
+    Scheme(Scheme, i16),
+    Cosmos(PathBuf),
+}
+
+impl FromStr for Hop {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let (name, argument) = text
+            .split_once(':')
+            .ok_or_else(|| format!("{text:?} is not `scheme:count` or `cosmos:file`"))?;
+        if name == "cosmos" {
+            return Ok(Hop::Cosmos(PathBuf::from(argument)));
+        }
+        let scheme = Scheme::from_str(name, true)?;
+        let count = argument
+            .parse::<i16>()
+            .ok()
+            .filter(|count| *count > 0)
+            .ok_or_else(|| format!("{argument:?} is not a partition count"))?;
+        scheme.check_partition_count(count)?;
+        Ok(Hop::Scheme(scheme, count))
+    }
+}
+
+/// A hop ready to place keys.
+pub struct Placer {
+    pub name: String,
+    pub partitioner: Box<dyn Partitioner>,
+    /// Label of every partition, the range id for Cosmos DB
+    pub labels: Vec<String>,
+}
+
+impl Hop {
+    pub fn build(&self) -> Result<Placer, String> {
+        let (partitioner, labels): (Box<dyn Partitioner>, Vec<String>) = match self {
+            Hop::Scheme(scheme, count) => (
+                scheme.partitioner(*count),
+                (0..*count).map(|partition| partition.to_string()).collect(),
+            ),
+            Hop::Cosmos(path) => {
+                let ranges = load_ranges(path)?;
+                let labels = ranges.iter().map(|range| range.id.clone()).collect();
+                (Box::new(CosmosRanges::new(ranges, HashVersion::V2)), labels)
+            }
+        };
+        Ok(Placer {
+            name: self.name(),
+            partitioner,
+            labels,
+        })
+    }
+
+    pub fn name(&self) -> String {
+        match self {
+            Hop::Scheme(scheme, count) => format!("{}:{count}", scheme.name()),
+            Hop::Cosmos(path) => format!("cosmos:{}", path.display()),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Colocation {
+    pub keys: u64,
+    pub weight: f64,
+    pub skipped_lines: u64,
+    /// Distinct partition paths through all hops, the number of separate affinity groups
+    pub paths: u64,
+    pub links: Vec<Link>,
+}
+
+/// How the partitions of one hop feed the partitions of the next.
+#[derive(Debug, Serialize)]
+pub struct Link {
+    pub from: String,
+    pub to: String,
+    pub from_labels: Vec<String>,
+    pub to_labels: Vec<String>,
+    /// Weight of the keys in partition `row` of `from` and `column` of `to`
+    pub matrix: Vec<Vec<f64>>,
+    /// Number of `to` partitions that every `from` partition feeds
+    pub fan_out: Vec<u64>,
+    /// Number of `from` partitions that feed every `to` partition
+    pub fan_in: Vec<u64>,
+    /// Share of the weight that goes to the main `to` partition of its `from` partition;
+    /// 1.0 when every upstream partition feeds a single downstream partition
+    pub affinity: f64,
+    /// Cramér's V of the matrix: 0 for independent placements, 1 for a one-to-one mapping
+    pub cramers_v: f64,
+}
+
+pub fn link(weights: &HashMap<String, f64>, from: &Placer, to: &Placer) -> Link {
+    let columns = to.labels.len();
+    let mut matrix = vec![vec![0.0; columns]; from.labels.len()];
+    for (key, weight) in weights {
+        let row = from.partitioner.partition_of(key) as usize;
+        matrix[row][to.partitioner.partition_of(key) as usize] += weight;
+    }
+
+    let fan_out: Vec<u64> = matrix
+        .iter()
+        .map(|row| row.iter().filter(|cell| **cell > 0.0).count() as u64)
+        .collect();
+    let fan_in: Vec<u64> = (0..columns)
+        .map(|column| matrix.iter().filter(|row| row[column] > 0.0).count() as u64)
+        .collect();
+    let total: f64 = matrix.iter().flatten().sum();
+    let dominant: f64 = matrix
+        .iter()
+        .map(|row| row.iter().copied().fold(0.0, f64::max))
+        .sum();
+
+    let test = chi_square(&matrix);
+    // Only partitions that receive keys count towards the dimensions, as in `chi_square`.
+    let used = |fan: &[u64]| {
+        fan.iter()
+            .filter(|count| **count > 0)
+            .count()
+            .saturating_sub(1)
+    };
+    let dimension = used(&fan_out).min(used(&fan_in)) as f64;
+    Link {
+        from: from.name.clone(),
+        to: to.name.clone(),
+        from_labels: from.labels.clone(),
+        to_labels: to.labels.clone(),
+        affinity: if total == 0.0 { 0.0 } else { dominant / total },
+        cramers_v: if total == 0.0 || dimension == 0.0 {
+            0.0
+        } else {
+            (test.statistic / (total * dimension)).sqrt().min(1.0)
+        },
+        matrix,
+        fan_out,
+        fan_in,
+    }
+}
+
+pub fn colocate(weights: &HashMap<String, f64>, hops: &[Hop]) -> Result<Colocation, String> {
+    let built = hops.iter().map(Hop::build).collect::<Result<Vec<_>, _>>()?;
+    let links = built
+        .windows(2)
+        .map(|pair| link(weights, &pair[0], &pair[1]))
+        .collect();
+    let paths: HashSet<Vec<u16>> = weights
+        .keys()
+        .map(|key| {
+            built
+                .iter()
+                .map(|placer| placer.partitioner.partition_of(key))
+                .collect()
+        })
+        .collect();
+    Ok(Colocation {
+        keys: weights.len() as u64,
+        weight: weights.values().sum(),
+        skipped_lines: 0,
+        paths: paths.len() as u64,
+        links,
+    })
+}
+
+pub fn run(args: &ColocateArgs) -> Result<(), String> {
+    if args.hops.len() < 2 {
+        return Err("--hops needs at least two hops".to_string());
+    }
+    let (weights, skipped_lines) = read_key_files(&args.files, &args.fields)?;
+    let mut report = colocate(&weights, &args.hops)?;
+    report.skipped_lines = skipped_lines;
+    if args.json {
+        return print_json(&report);
+    }
+
+    println!(
+        "{} keys, {} lines skipped, {} distinct partition paths through {} hops",
+        report.keys,
+        report.skipped_lines,
+        report.paths,
+        args.hops.len()
+    );
+    for link in &report.links {
+        let mean = |values: &[u64]| values.iter().sum::<u64>() as f64 / values.len() as f64;
+        println!();
+        println!(
+            "{} → {}: mean fan-out {:.2} (max {}), mean fan-in {:.2} (max {}), affinity {:.1}%, Cramér's V {:.3}",
+            link.from,
+            link.to,
+            mean(&link.fan_out),
+            link.fan_out.iter().max().unwrap_or(&0),
+            mean(&link.fan_in),
+            link.fan_in.iter().max().unwrap_or(&0),
+            100.0 * link.affinity,
+            link.cramers_v
+        );
+        if args.matrix {
+            print_matrix(link);
+        }
+    }
+    Ok(())
+}
+
+fn print_matrix(link: &Link) {
+    let width = link
+        .matrix
+        .iter()
+        .flatten()
+        .map(|cell| format_weight(*cell).len())
+        .chain(link.to_labels.iter().map(String::len))
+        .max()
+        .unwrap_or(1);
+    let row_width = link.from_labels.iter().map(String::len).max().unwrap_or(1);
+    print!("{:>row_width$} |", "");
+    for label in &link.to_labels {
+        print!(" {label:>width$}");
+    }
+    println!();
+    for (label, row) in link.from_labels.iter().zip(&link.matrix) {
+        print!("{label:>row_width$} |");
+        for cell in row {
+            if *cell == 0.0 {
+                print!(" {:>width$}", ".");
+            } else {
+                print!(" {:>width$}", format_weight(*cell));
+            }
+        }
+        println!();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_colocation_across_hops() -> Result<(), String> {
+        let weights: HashMap<String, f64> = (0..3000).map(|i| (format!("key-{i}"), 1.0)).collect();
+        let hops: Vec<Hop> = ["service-bus:8", "service-bus:4", "kafka:4"]
+            .iter()
+            .map(|hop| hop.parse())
+            .collect::<Result<_, _>>()?;
+        let report = colocate(&weights, &hops)?;
+
+        // Halving the contiguous ranges merges neighbouring partitions: a perfect affinity.
+        let halved = &report.links[0];
+        assert_eq!(halved.fan_out, vec![1; 8]);
+        assert_eq!(halved.fan_in, vec![2; 4]);
+        assert_eq!(halved.affinity, 1.0);
+        assert!((halved.cramers_v - 1.0).abs() < 1e-9);
+
+        // A different hash scatters every partition over all of the next hop's partitions.
+        let rehashed = &report.links[1];
+        assert_eq!(rehashed.fan_out, vec![4; 4]);
+        assert!(rehashed.affinity < 0.4 && rehashed.cramers_v < 0.1);
+        assert_eq!(report.paths, 32);
+        assert_eq!(
+            rehashed.matrix.iter().flatten().sum::<f64>(),
+            weights.len() as f64
+        );
+
+        assert_eq!("cosmos:pk.json".parse(), Ok(Hop::Cosmos("pk.json".into())));
+        assert!("kafka".parse::<Hop>().is_err() && "kafka:0".parse::<Hop>().is_err());
+        Ok(())
+    }
+}
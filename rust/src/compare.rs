@@ -0,0 +1,183 @@
+use clap::Args;
+use serde::Serialize;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+
+use crate::analyze::{analyze_lines, format_weight, Report};
+use crate::hll;
+use crate::partitioner::Scheme;
+use crate::print_json;
+use crate::records::RecordFields;
+use crate::stats::{chi_square, ChiSquare};
+
+#[derive(Args, Debug)]
+pub struct CompareArgs {
+    /// Key file of the current scheme, one record per line
+    left: PathBuf,
+
+    /// Key file of the scheme to compare against
+    right: PathBuf,
+
+    /// Number of partitions
+    #[clap(short = 'c', long, value_parser = clap::value_parser!(i16).range(1..))]
+    partition_count: i16,
+
+    /// Partitioning scheme that places the keys
+    #[clap(long, value_enum, default_value_t)]
+    scheme: Scheme,
+
+    #[command(flatten)]
+    fields: RecordFields,
+
+    /// Significance level below which the distributions count as different
+    #[clap(long, default_value_t = 0.05)]
+    alpha: f64,
+
+    /// Print JSON instead of a table
+    #[clap(long)]
+    json: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Comparison {
+    pub left: Report,
+    pub right: Report,
+    /// Right minus left
+    pub max_to_mean_change: f64,
+    pub variation_change: f64,
+    /// Chi-square test of homogeneity over the per-partition messages of both key sets,
+    /// treating every unit of weight as an independent message
+    pub test: ChiSquare,
+    pub distributions_differ: bool,
+}
+
+pub fn compare(left: Report, right: Report, alpha: f64) -> Comparison {
+    let row = |report: &Report| -> Vec<f64> {
+        report
+            .partitions
+            .iter()
+            .map(|partition| partition.messages)
+            .collect()
+    };
+    let test = chi_square(&[row(&left), row(&right)]);
+    Comparison {
+        max_to_mean_change: right.max_to_mean - left.max_to_mean,
+        variation_change: right.variation - left.variation,
+        distributions_differ: test.p_value < alpha,
+        test,
+        left,
+        right,
+    }
+}
+
+fn analyze_file(path: &Path, args: &CompareArgs) -> Result<Report, String> {
+    let file =
+        std::fs::File::open(path).map_err(|e| format!("cannot open {}: {e}", path.display()))?;
+    let analysis = analyze_lines(
+        BufReader::new(file),
+        args.scheme,
+        args.partition_count,
+        &args.fields,
+        1,
+        hll::DEFAULT_PRECISION,
+    )
+    .map_err(|e| format!("cannot read {}: {e}", path.display()))?;
+    Ok(analysis.report(0))
+}
+
+pub fn run(args: &CompareArgs) -> Result<(), String> {
+    args.scheme.check_partition_count(args.partition_count)?;
+    if !(0.0..=1.0).contains(&args.alpha) {
+        return Err(format!("--alpha {} is not between 0 and 1", args.alpha));
+    }
+    let comparison = compare(
+        analyze_file(&args.left, args)?,
+        analyze_file(&args.right, args)?,
+        args.alpha,
+    );
+    if args.json {
+        return print_json(&comparison);
+    }
+
+    let (left, right) = (&comparison.left, &comparison.right);
+    println!(
+        "{:>12} {:>10} {:>7} {:>10} {:>7} {:>8}",
+        "partition_id", "left", "share", "right", "share", "change"
+    );
+    for (l, r) in left.partitions.iter().zip(&right.partitions) {
+        println!(
+            "{:>12} {:>10} {:>6.2}% {:>10} {:>6.2}% {:>+7.2}%",
+            l.partition_id,
+            format_weight(l.messages),
+            100.0 * l.share,
+            format_weight(r.messages),
+            100.0 * r.share,
+            100.0 * (r.share - l.share)
+        );
+    }
+    println!();
+    println!("{:>14} {:>10} {:>10} {:>10}", "", "left", "right", "change");
+    println!(
+        "{:>14} {:>10} {:>10}",
+        "messages",
+        format_weight(left.messages),
+        format_weight(right.messages)
+    );
+    println!(
+        "{:>14} {:>10} {:>10}",
+        "distinct keys", left.distinct_keys, right.distinct_keys
+    );
+    println!(
+        "{:>14} {:>10.3} {:>10.3} {:>+10.3}",
+        "max/mean", left.max_to_mean, right.max_to_mean, comparison.max_to_mean_change
+    );
+    println!(
+        "{:>14} {:>10.3} {:>10.3} {:>+10.3}",
+        "std/mean", left.variation, right.variation, comparison.variation_change
+    );
+    println!();
+    println!(
+        "chi-square {:.3} with {} degrees of freedom, p = {:.4}: {}",
+        comparison.test.statistic,
+        comparison.test.degrees_of_freedom,
+        comparison.test.p_value,
+        if comparison.distributions_differ {
+            format!("the distributions differ at alpha = {}", args.alpha)
+        } else {
+            format!("no significant difference at alpha = {}", args.alpha)
+        }
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_balanced_keys_beat_a_hot_key() -> Result<(), String> {
+        let fields = RecordFields::default();
+        let hot: String = (0..400)
+            .map(|i| {
+                if i % 2 == 0 {
+                    "hot\n".to_string()
+                } else {
+                    format!("order-{i}\n")
+                }
+            })
+            .collect();
+        let balanced: String = (0..400).map(|i| format!("customer-{i}\n")).collect();
+        let analyze = |text: &str| -> Result<Report, String> {
+            Ok(analyze_lines(text.as_bytes(), Scheme::ServiceBus, 8, &fields, 1, 8)?.report(0))
+        };
+
+        let comparison = compare(analyze(&hot)?, analyze(&balanced)?, 0.05);
+        assert!(comparison.max_to_mean_change < -1.0);
+        assert!(comparison.distributions_differ);
+        assert_eq!(comparison.test.degrees_of_freedom, 7);
+
+        let comparison = compare(analyze(&balanced)?, analyze(&balanced)?, 0.05);
+        assert!(!comparison.distributions_differ);
+        Ok(())
+    }
+}
@@ -0,0 +1,109 @@
+//! Consistent-hash partitioners that move few keys when the partition count changes:
+//! Jump Consistent Hash (Lamping and Veach, 2014) and rendezvous (highest random weight)
+//! hashing. Both hash the key bytes with the seeded lookup3 `hashlittle2` used elsewhere.
+
+use crate::hash_seeded;
+use crate::partitioner::Partitioner;
+
+/// `(key, buckets, bucket)` cases from the test suite of the go-jump implementation.
+pub const JUMP_VECTORS: &[(u64, i32, i32)] = &[
+    (1, 1, 0),
+    (42, 57, 43),
+    (0xDEAD_10CC, 1, 0),
+    (0xDEAD_10CC, 666, 361),
+    (256, 1024, 520),
+];
+
+/// Jump Consistent Hash of a 64-bit key over `buckets` buckets.
+pub fn jump_hash(mut key: u64, buckets: i32) -> i32 {
+    let (mut b, mut j) = (-1i64, 0i64);
+    while j < buckets as i64 {
+        b = j;
+        key = key.wrapping_mul(2_862_933_555_777_941_757).wrapping_add(1);
+        j = ((b + 1) as f64 * ((1u64 << 31) as f64 / ((key >> 33) + 1) as f64)) as i64;
+    }
+    b as i32
+}
+
+fn key_hash(key: &[u8], seed: u32) -> u64 {
+    hash_seeded(key, seed, 0).2
+}
+
+#[derive(Debug, Clone)]
+pub struct JumpHash {
+    partition_count: i16,
+}
+
+impl JumpHash {
+    pub fn new(partition_count: i16) -> Self {
+        JumpHash { partition_count }
+    }
+}
+
+impl Partitioner for JumpHash {
+    fn partition_count(&self) -> i16 {
+        self.partition_count
+    }
+
+    fn partition(&self, key: &[u8]) -> u16 {
+        jump_hash(key_hash(key, 0), self.partition_count as i32) as u16
+    }
+}
+
+/// Rendezvous hashing: every partition scores the key with its own seed and the highest
+/// score wins, so adding a partition only takes over the keys it now scores highest on.
+#[derive(Debug, Clone)]
+pub struct Rendezvous {
+    partition_count: i16,
+}
+
+impl Rendezvous {
+    pub fn new(partition_count: i16) -> Self {
+        Rendezvous { partition_count }
+    }
+}
+
+impl Partitioner for Rendezvous {
+    fn partition_count(&self) -> i16 {
+        self.partition_count
+    }
+
+    fn partition(&self, key: &[u8]) -> u16 {
+        (0..self.partition_count as u16)
+            .max_by_key(|partition| (key_hash(key, *partition as u32 + 1), *partition))
+            .unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_jump_hash_vectors() {
+        for (key, buckets, expected) in JUMP_VECTORS {
+            assert_eq!(jump_hash(*key, *buckets), *expected, "{key} {buckets}");
+        }
+    }
+
+    #[test]
+    fn test_growing_only_moves_keys_to_the_new_partition() {
+        let keys: Vec<String> = (0..2000).map(|i| format!("tenant-{i}")).collect();
+        let partitioners: [(Box<dyn Partitioner>, Box<dyn Partitioner>); 2] = [
+            (Box::new(JumpHash::new(9)), Box::new(JumpHash::new(10))),
+            (Box::new(Rendezvous::new(9)), Box::new(Rendezvous::new(10))),
+        ];
+        for (before, after) in &partitioners {
+            let mut moved = 0;
+            for key in &keys {
+                let (from, to) = (before.partition_of(key), after.partition_of(key));
+                if from != to {
+                    assert_eq!(to, 9);
+                    moved += 1;
+                }
+            }
+            // About a tenth of the keys move to the new partition.
+            assert!((150..250).contains(&moved), "{moved}");
+        }
+    }
+}
@@ -0,0 +1,571 @@
+//! Cosmos DB effective partition keys (EPK) and their placement on physical partitions.
+//!
+//! The encodings follow `PartitionKeyInternal` of the Cosmos DB SDKs:
+//! - hash v1 hashes the components with MurmurHash3-32 and prefixes the binary encoding of the
+//!   (truncated) components with the hash as a number component;
+//! - hash v2 hashes them with MurmurHash3-128 and keeps only the hash;
+//! - hierarchical (multi-hash) keys concatenate the v2 hashes of the individual components.
+
+use clap::Args;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::io::BufRead;
+use std::path::{Path, PathBuf};
+
+use crate::get_partition_id;
+use crate::partitioner::Partitioner;
+use crate::print_json;
+
+/// Effective partition keys computed by the Cosmos DB SDKs (`PartitionKeyInternal` tests).
+#[rustfmt::skip]
+pub const EPK_VECTORS: &[(&str, HashVersion, &str)] = &[
+    (r#"[""]"#,             HashVersion::V1, "05C1CF33970FF80800"),
+    (r#"["partitionKey"]"#, HashVersion::V1, "05C1E1B3D9CD2608716273756A756A706F4C667A00"),
+    (r#"["redmond"]"#,      HashVersion::V1, "05C1EFE313830C087366656E706F6500"),
+    (r#"[true]"#,           HashVersion::V1, "05C1D7C5A903D803"),
+    (r#"[false]"#,          HashVersion::V1, "05C1DB857D857C02"),
+    (r#"[null]"#,           HashVersion::V1, "05C1ED45D7475601"),
+    (r#"[""]"#,             HashVersion::V2, "32E9366E637A71B4E710384B2F4970A0"),
+    (r#"["partitionKey"]"#, HashVersion::V2, "013AEFCF77FA271571CF665A58C933F1"),
+    (r#"["redmond"]"#,      HashVersion::V2, "22E342F38A486A088463DFF7838A5963"),
+    (r#"[true]"#,           HashVersion::V2, "0E711127C5B5A8E4726AC6DD306A3E59"),
+    (r#"[false]"#,          HashVersion::V2, "2FE1BE91E90A3439635E0E9E37361EF2"),
+    (r#"[null]"#,           HashVersion::V2, "378867E4430E67857ACE5C908374FE16"),
+    (r#"[5]"#,              HashVersion::V2, "19C08621B135968252FB34B4CF66F811"),
+];
+
+/// Strings longer than this many UTF-16 units (.NET chars) are truncated before v1 hashing.
+const V1_MAX_STRING_UNITS: usize = 100;
+/// UTF-8 bytes of a string that the v1 binary encoding keeps.
+const V1_MAX_STRING_BYTES: usize = 100;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum HashVersion {
+    /// MurmurHash3-32 followed by the encoded key (containers created before hash v2)
+    V1,
+    /// MurmurHash3-128 (the default for new containers and required for hierarchical keys)
+    #[default]
+    V2,
+}
+
+/// One level of a partition key value.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Component {
+    Undefined,
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+}
+
+/// `PartitionKeyComponentType` markers of the binary encodings.
+mod marker {
+    pub const UNDEFINED: u8 = 0x00;
+    pub const NULL: u8 = 0x01;
+    pub const FALSE: u8 = 0x02;
+    pub const TRUE: u8 = 0x03;
+    pub const NUMBER: u8 = 0x05;
+    pub const STRING: u8 = 0x08;
+}
+
+impl Component {
+    fn from_json(value: &Value) -> Result<Component, String> {
+        match value {
+            Value::Null => Ok(Component::Null),
+            Value::Bool(value) => Ok(Component::Bool(*value)),
+            Value::Number(number) => number
+                .as_f64()
+                .map(Component::Number)
+                .ok_or_else(|| format!("{number} is not a double")),
+            Value::String(value) => Ok(Component::String(value.clone())),
+            Value::Object(object) if object.is_empty() => Ok(Component::Undefined),
+            other => Err(format!("{other} is not a partition key component")),
+        }
+    }
+
+    fn write_for_hashing(&self, version: HashVersion, out: &mut Vec<u8>) {
+        match self {
+            Component::Undefined => out.push(marker::UNDEFINED),
+            Component::Null => out.push(marker::NULL),
+            Component::Bool(false) => out.push(marker::FALSE),
+            Component::Bool(true) => out.push(marker::TRUE),
+            Component::Number(value) => {
+                out.push(marker::NUMBER);
+                out.extend_from_slice(&value.to_le_bytes());
+            }
+            Component::String(value) => {
+                out.push(marker::STRING);
+                out.extend_from_slice(value.as_bytes());
+                out.push(match version {
+                    HashVersion::V1 => 0x00,
+                    HashVersion::V2 => 0xFF,
+                });
+            }
+        }
+    }
+
+    /// The order-preserving binary encoding that v1 appends after the hash.
+    fn write_for_binary_encoding(&self, out: &mut Vec<u8>) {
+        match self {
+            Component::Number(value) => {
+                out.push(marker::NUMBER);
+                let mut payload = encode_double(*value);
+                out.push((payload >> 56) as u8);
+                payload <<= 8;
+                let mut byte = None;
+                while payload != 0 {
+                    out.extend(byte);
+                    byte = Some((payload >> 56) as u8 | 0x01);
+                    payload <<= 7;
+                }
+                out.push(byte.unwrap_or(0x01) & 0xFE);
+            }
+            Component::String(value) => {
+                out.push(marker::STRING);
+                let bytes = value.as_bytes();
+                let short = bytes.len() <= V1_MAX_STRING_BYTES;
+                let kept = if short {
+                    bytes
+                } else {
+                    &bytes[..=V1_MAX_STRING_BYTES]
+                };
+                out.extend(kept.iter().map(|byte| byte.saturating_add(1)));
+                if short {
+                    out.push(0x00);
+                }
+            }
+            other => other.write_for_hashing(HashVersion::V1, out),
+        }
+    }
+
+    fn truncate_for_v1(&self) -> Component {
+        match self {
+            Component::String(value) if value.encode_utf16().count() > V1_MAX_STRING_UNITS => {
+                let mut kept = String::new();
+                let mut units = 0;
+                for ch in value.chars() {
+                    units += ch.len_utf16();
+                    if units > V1_MAX_STRING_UNITS {
+                        // A cut through a surrogate pair leaves a lone surrogate, which .NET
+                        // encodes as U+FFFD.
+                        if units - ch.len_utf16() < V1_MAX_STRING_UNITS {
+                            kept.push(char::REPLACEMENT_CHARACTER);
+                        }
+                        break;
+                    }
+                    kept.push(ch);
+                }
+                Component::String(kept)
+            }
+            other => other.clone(),
+        }
+    }
+}
+
+/// Sign-magnitude to offset encoding, so that the encoded doubles sort like the numbers.
+fn encode_double(value: f64) -> u64 {
+    let bits = value.to_bits();
+    const SIGN: u64 = 0x8000_0000_0000_0000;
+    if bits < SIGN {
+        bits ^ SIGN
+    } else {
+        (!bits).wrapping_add(1)
+    }
+}
+
+/// Parses a partition key: a JSON array of components (`["tenant-1", 42]`) or a bare string.
+pub fn parse_partition_key(text: &str) -> Result<Vec<Component>, String> {
+    if !text.trim_start().starts_with('[') {
+        return Ok(vec![Component::String(text.to_string())]);
+    }
+    let value: Value = serde_json::from_str(text).map_err(|e| format!("{text}: {e}"))?;
+    let components = value
+        .as_array()
+        .ok_or_else(|| format!("{text} is not a JSON array"))?
+        .iter()
+        .map(Component::from_json)
+        .collect::<Result<Vec<_>, _>>()?;
+    if components.is_empty() {
+        return Err(format!("{text} has no components"));
+    }
+    Ok(components)
+}
+
+/// Effective partition key as the upper-case hex string that the service compares.
+/// With more than one component and hash v2 this is the hierarchical (multi-hash) EPK.
+pub fn effective_partition_key(components: &[Component], version: HashVersion) -> String {
+    match version {
+        HashVersion::V1 => {
+            let truncated: Vec<Component> =
+                components.iter().map(Component::truncate_for_v1).collect();
+            let mut hashed = Vec::new();
+            for component in &truncated {
+                component.write_for_hashing(version, &mut hashed);
+            }
+            let mut encoded = Vec::new();
+            Component::Number(murmur3_32(&hashed, 0) as f64)
+                .write_for_binary_encoding(&mut encoded);
+            for component in &truncated {
+                component.write_for_binary_encoding(&mut encoded);
+            }
+            to_hex(&encoded)
+        }
+        HashVersion::V2 => components
+            .iter()
+            .map(|component| {
+                let mut hashed = Vec::new();
+                component.write_for_hashing(version, &mut hashed);
+                let (h1, h2) = murmur3_128(&hashed, 0);
+                let mut hash = [0u8; 16];
+                hash[..8].copy_from_slice(&h2.to_be_bytes());
+                hash[8..].copy_from_slice(&h1.to_be_bytes());
+                // The two top bits are cleared so that every hash sorts below the "FF" maximum.
+                hash[0] &= 0x3F;
+                to_hex(&hash)
+            })
+            .collect(),
+    }
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02X}")).collect()
+}
+
+/// MurmurHash3 x86 32-bit.
+pub fn murmur3_32(data: &[u8], seed: u32) -> u32 {
+    const C1: u32 = 0xcc9e_2d51;
+    const C2: u32 = 0x1b87_3593;
+
+    let mut h = seed;
+    let mut chunks = data.chunks_exact(4);
+    for chunk in &mut chunks {
+        let mut k = u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+        k = k.wrapping_mul(C1).rotate_left(15).wrapping_mul(C2);
+        h ^= k;
+        h = h.rotate_left(13).wrapping_mul(5).wrapping_add(0xe654_6b64);
+    }
+    let tail = chunks.remainder();
+    if !tail.is_empty() {
+        let mut k = 0u32;
+        for (i, byte) in tail.iter().enumerate() {
+            k |= (*byte as u32) << (8 * i);
+        }
+        h ^= k.wrapping_mul(C1).rotate_left(15).wrapping_mul(C2);
+    }
+    h ^= data.len() as u32;
+    h ^= h >> 16;
+    h = h.wrapping_mul(0x85eb_ca6b);
+    h ^= h >> 13;
+    h = h.wrapping_mul(0xc2b2_ae35);
+    h ^ (h >> 16)
+}
+
+/// MurmurHash3 x64 128-bit, returned as its two 64-bit halves `(h1, h2)`.
+pub fn murmur3_128(data: &[u8], seed: u64) -> (u64, u64) {
+    const C1: u64 = 0x87c3_7b91_1142_53d5;
+    const C2: u64 = 0x4cf5_ad43_2745_937f;
+
+    fn fmix(mut k: u64) -> u64 {
+        k ^= k >> 33;
+        k = k.wrapping_mul(0xff51_afd7_ed55_8ccd);
+        k ^= k >> 33;
+        k = k.wrapping_mul(0xc4ce_b9fe_1a85_ec53);
+        k ^ (k >> 33)
+    }
+    let mix_k1 = |k: u64| k.wrapping_mul(C1).rotate_left(31).wrapping_mul(C2);
+    let mix_k2 = |k: u64| k.wrapping_mul(C2).rotate_left(33).wrapping_mul(C1);
+
+    let (mut h1, mut h2) = (seed, seed);
+    let mut chunks = data.chunks_exact(16);
+    for chunk in &mut chunks {
+        let k1 = u64::from_le_bytes(chunk[..8].try_into().unwrap());
+        let k2 = u64::from_le_bytes(chunk[8..].try_into().unwrap());
+        h1 ^= mix_k1(k1);
+        h1 = h1
+            .rotate_left(27)
+            .wrapping_add(h2)
+            .wrapping_mul(5)
+            .wrapping_add(0x52dc_e729);
+        h2 ^= mix_k2(k2);
+        h2 = h2
+            .rotate_left(31)
+            .wrapping_add(h1)
+            .wrapping_mul(5)
+            .wrapping_add(0x3849_5ab5);
+    }
+    let tail = chunks.remainder();
+    let lane = |bytes: &[u8]| {
+        bytes
+            .iter()
+            .enumerate()
+            .fold(0u64, |k, (i, byte)| k | (*byte as u64) << (8 * i))
+    };
+    if tail.len() > 8 {
+        h2 ^= mix_k2(lane(&tail[8..]));
+    }
+    if !tail.is_empty() {
+        h1 ^= mix_k1(lane(&tail[..tail.len().min(8)]));
+    }
+
+    h1 ^= data.len() as u64;
+    h2 ^= data.len() as u64;
+    h1 = h1.wrapping_add(h2);
+    h2 = h2.wrapping_add(h1);
+    h1 = fmix(h1);
+    h2 = fmix(h2);
+    h1 = h1.wrapping_add(h2);
+    h2 = h2.wrapping_add(h1);
+    (h1, h2)
+}
+
+/// A physical partition's EPK interval `[min_inclusive, max_exclusive)`, as returned by the
+/// `pkranges` REST resource.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PartitionKeyRange {
+    pub id: String,
+    pub min_inclusive: String,
+    pub max_exclusive: String,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RangeFile {
+    Feed {
+        #[serde(rename = "PartitionKeyRanges")]
+        ranges: Vec<PartitionKeyRange>,
+    },
+    List(Vec<PartitionKeyRange>),
+}
+
+/// Parses a range list: the `pkranges` feed (`{"PartitionKeyRanges": [...]}`) or a bare array.
+/// The ranges must tile the EPK space from `""` to `"FF"` without gaps or overlaps.
+pub fn parse_ranges(text: &str) -> Result<Vec<PartitionKeyRange>, String> {
+    let mut ranges = match serde_json::from_str(text).map_err(|e| e.to_string())? {
+        RangeFile::Feed { ranges } | RangeFile::List(ranges) => ranges,
+    };
+    for range in &mut ranges {
+        range.min_inclusive.make_ascii_uppercase();
+        range.max_exclusive.make_ascii_uppercase();
+    }
+    ranges.sort_by(|a, b| a.min_inclusive.cmp(&b.min_inclusive));
+
+    let mut expected_min = "";
+    for range in &ranges {
+        if range.min_inclusive != expected_min {
+            return Err(format!(
+                "range {} starts at {:?} but the previous range ends at {expected_min:?}",
+                range.id, range.min_inclusive
+            ));
+        }
+        if range.max_exclusive <= range.min_inclusive {
+            return Err(format!("range {} is empty", range.id));
+        }
+        expected_min = &range.max_exclusive;
+    }
+    if expected_min != "FF" {
+        return Err(format!(
+            "the ranges end at {expected_min:?} instead of \"FF\""
+        ));
+    }
+    Ok(ranges)
+}
+
+pub fn load_ranges(path: &Path) -> Result<Vec<PartitionKeyRange>, String> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| format!("cannot read {}: {e}", path.display()))?;
+    parse_ranges(&text).map_err(|e| format!("{}: {e}", path.display()))
+}
+
+/// The range that owns an EPK; `ranges` must be sorted and gap free, as from [`parse_ranges`].
+pub fn physical_partition<'a>(
+    ranges: &'a [PartitionKeyRange],
+    epk: &str,
+) -> Option<&'a PartitionKeyRange> {
+    let index = ranges.partition_point(|range| range.max_exclusive.as_str() <= epk);
+    ranges.get(index)
+}
+
+/// Physical partitions of a container as a [`Partitioner`]: a string key is hashed as a
+/// single-component partition key, and the partition id is the position of the owning range.
+#[derive(Debug, Clone)]
+pub struct CosmosRanges {
+    pub ranges: Vec<PartitionKeyRange>,
+    version: HashVersion,
+}
+
+impl CosmosRanges {
+    pub fn new(ranges: Vec<PartitionKeyRange>, version: HashVersion) -> Self {
+        CosmosRanges { ranges, version }
+    }
+}
+
+impl Partitioner for CosmosRanges {
+    fn partition_count(&self) -> i16 {
+        self.ranges.len() as i16
+    }
+
+    fn partition(&self, key: &[u8]) -> u16 {
+        self.partition_of(&String::from_utf8_lossy(key))
+    }
+
+    fn partition_of(&self, key: &str) -> u16 {
+        let epk = effective_partition_key(&[Component::String(key.to_string())], self.version);
+        // The ranges from `parse_ranges` cover every EPK, so the index is always in bounds.
+        self.ranges
+            .partition_point(|range| range.max_exclusive.as_str() <= epk.as_str()) as u16
+    }
+}
+
+#[derive(Args, Debug)]
+pub struct CosmosArgs {
+    /// Partition keys: a bare string or a JSON array of components such as
+    /// '["tenant-1", 42]' (hierarchical with hash v2); reads one per line from stdin when omitted
+    keys: Vec<String>,
+
+    /// Hash version of the container's partition key definition
+    #[clap(long = "hash", value_enum, default_value_t)]
+    version: HashVersion,
+
+    /// Partition key ranges of the container (`pkranges` feed JSON) to resolve physical partitions
+    #[clap(long)]
+    ranges: Option<PathBuf>,
+
+    /// Also print the Service Bus partition of the first component for this many partitions
+    #[clap(short = 'c', long, value_parser = clap::value_parser!(i16).range(1..))]
+    partition_count: Option<i16>,
+
+    /// Print JSON instead of a table
+    #[clap(long)]
+    json: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Placement {
+    pub key: String,
+    pub effective_partition_key: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub physical_partition: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub service_bus_partition_id: Option<u16>,
+}
+
+pub fn run(args: &CosmosArgs) -> Result<(), String> {
+    let keys = if args.keys.is_empty() {
+        std::io::stdin()
+            .lock()
+            .lines()
+            .filter(|line| !matches!(line, Ok(line) if line.is_empty()))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?
+    } else {
+        args.keys.clone()
+    };
+    let ranges = args.ranges.as_deref().map(load_ranges).transpose()?;
+
+    let mut placements = Vec::with_capacity(keys.len());
+    for key in keys {
+        let components = parse_partition_key(&key)?;
+        let effective_partition_key = effective_partition_key(&components, args.version);
+        let physical_partition = match &ranges {
+            Some(ranges) => Some(
+                physical_partition(ranges, &effective_partition_key)
+                    .ok_or_else(|| format!("no range owns {effective_partition_key}"))?
+                    .id
+                    .clone(),
+            ),
+            None => None,
+        };
+        let service_bus_partition_id = match (args.partition_count, &components[0]) {
+            (Some(count), Component::String(value)) => Some(get_partition_id(count, value)),
+            (Some(_), _) => {
+                return Err(format!(
+                    "{key}: the first component must be a string to place it on Service Bus"
+                ))
+            }
+            (None, _) => None,
+        };
+        placements.push(Placement {
+            key,
+            effective_partition_key,
+            physical_partition,
+            service_bus_partition_id,
+        });
+    }
+    if args.json {
+        return print_json(&placements);
+    }
+
+    for placement in &placements {
+        let mut line = placement.effective_partition_key.clone();
+        if let Some(range) = &placement.physical_partition {
+            line.push_str(&format!("\t{range}"));
+        }
+        if let Some(partition_id) = placement.service_bus_partition_id {
+            line.push_str(&format!("\t{partition_id}"));
+        }
+        println!("{line}\t{}", placement.key);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_effective_partition_keys_match_sdk() -> Result<(), String> {
+        for (key, version, expected) in EPK_VECTORS {
+            let components = parse_partition_key(key)?;
+            assert_eq!(
+                effective_partition_key(&components, *version),
+                *expected,
+                "{key} {version:?}"
+            );
+        }
+        let hierarchical = parse_partition_key(r#"["redmond", "partitionKey"]"#)?;
+        assert_eq!(
+            effective_partition_key(&hierarchical, HashVersion::V2),
+            "22E342F38A486A088463DFF7838A5963013AEFCF77FA271571CF665A58C933F1"
+        );
+        // v1 appends 5.0 as 05 C0 14: the offset-encoded exponent byte, then 7-bit groups
+        let number = parse_partition_key("[5]")?;
+        assert_eq!(
+            effective_partition_key(&number, HashVersion::V1),
+            "05C1D9C1C5517C05C014"
+        );
+
+        // v1 truncates at 100 UTF-16 units, so 60 emoji hash like 50, and a cut through the
+        // pair after `a` and 49 emoji leaves U+FFFD.
+        let v1 =
+            |text: String| effective_partition_key(&[Component::String(text)], HashVersion::V1);
+        assert_eq!(v1("😀".repeat(60)), v1("😀".repeat(50)));
+        assert_ne!(v1("😀".repeat(50)), v1("😀".repeat(49)));
+        assert_eq!(
+            v1(format!("a{}", "😀".repeat(60))),
+            v1(format!("a{}\u{FFFD}", "😀".repeat(49)))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_physical_partition() -> Result<(), String> {
+        let ranges = parse_ranges(
+            r#"{"PartitionKeyRanges": [
+                {"id": "1", "minInclusive": "1FFFFFFFFFFFFFFF", "maxExclusive": "FF"},
+                {"id": "0", "minInclusive": "", "maxExclusive": "1FFFFFFFFFFFFFFF"}
+            ]}"#,
+        )?;
+        let owner = |key: &str| -> Result<String, String> {
+            let epk = effective_partition_key(&parse_partition_key(key)?, HashVersion::V2);
+            Ok(physical_partition(&ranges, &epk).unwrap().id.clone())
+        };
+        assert_eq!(owner("partitionKey")?, "0"); // 013AEF...
+        assert_eq!(owner("redmond")?, "1"); // 22E342...
+        assert!(
+            parse_ranges(r#"[{"id": "0", "minInclusive": "", "maxExclusive": "80"}]"#).is_err()
+        );
+        Ok(())
+    }
+}
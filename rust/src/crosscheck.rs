@@ -0,0 +1,188 @@
+use clap::Args;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use crate::analyze::format_weight;
+use crate::partitioner::Scheme;
+use crate::print_json;
+use crate::records::{read_key_files, RecordFields};
+
+#[derive(Args, Debug)]
+pub struct CrosscheckArgs {
+    /// Key files with one record per line (reads stdin when omitted)
+    files: Vec<PathBuf>,
+
+    /// Number of partitions
+    #[clap(short = 'c', long, value_parser = clap::value_parser!(i16).range(1..))]
+    partition_count: i16,
+
+    /// Scheme of the first client path, e.g. AMQP producers
+    #[clap(long, value_enum, default_value_t = Scheme::ServiceBus)]
+    left: Scheme,
+
+    /// Scheme of the second client path, e.g. producers on the Kafka endpoint
+    #[clap(long, value_enum, default_value_t = Scheme::Kafka)]
+    right: Scheme,
+
+    #[command(flatten)]
+    fields: RecordFields,
+
+    /// Number of disagreeing keys to list, heaviest first
+    #[clap(short = 'n', long, default_value_t = 20)]
+    show: usize,
+
+    /// Print JSON instead of a table
+    #[clap(long)]
+    json: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Crosscheck {
+    pub left: Scheme,
+    pub right: Scheme,
+    pub partition_count: i16,
+    pub keys: u64,
+    pub weight: f64,
+    pub skipped_lines: u64,
+    pub disagreeing_keys: u64,
+    pub disagreeing_weight: f64,
+    /// Disagreeing keys, heaviest first
+    pub disagreements: Vec<Disagreement>,
+}
+
+/// A key that the two client paths send to different partitions, so messages with this key
+/// lose their relative order as soon as both paths are used.
+#[derive(Debug, Serialize, PartialEq)]
+pub struct Disagreement {
+    pub key: String,
+    pub weight: f64,
+    pub left_partition_id: u16,
+    pub right_partition_id: u16,
+}
+
+pub fn crosscheck(
+    weights: &HashMap<String, f64>,
+    left: Scheme,
+    right: Scheme,
+    partition_count: i16,
+) -> Crosscheck {
+    let (left_partitioner, right_partitioner) = (
+        left.partitioner(partition_count),
+        right.partitioner(partition_count),
+    );
+    let mut disagreements: Vec<Disagreement> = weights
+        .iter()
+        .filter_map(|(key, weight)| {
+            let left_partition_id = left_partitioner.partition_of(key);
+            let right_partition_id = right_partitioner.partition_of(key);
+            (left_partition_id != right_partition_id).then(|| Disagreement {
+                key: key.clone(),
+                weight: *weight,
+                left_partition_id,
+                right_partition_id,
+            })
+        })
+        .collect();
+    disagreements.sort_by(|a, b| {
+        b.weight
+            .total_cmp(&a.weight)
+            .then_with(|| a.key.cmp(&b.key))
+    });
+
+    Crosscheck {
+        left,
+        right,
+        partition_count,
+        keys: weights.len() as u64,
+        weight: weights.values().sum(),
+        skipped_lines: 0,
+        disagreeing_keys: disagreements.len() as u64,
+        disagreeing_weight: disagreements.iter().map(|entry| entry.weight).sum(),
+        disagreements,
+    }
+}
+
+pub fn run(args: &CrosscheckArgs) -> Result<(), String> {
+    args.left.check_partition_count(args.partition_count)?;
+    args.right.check_partition_count(args.partition_count)?;
+    let (weights, skipped_lines) = read_key_files(&args.files, &args.fields)?;
+    let mut report = crosscheck(&weights, args.left, args.right, args.partition_count);
+    report.skipped_lines = skipped_lines;
+    report.disagreements.truncate(args.show);
+    if args.json {
+        return print_json(&report);
+    }
+
+    let share = |part: f64, total: f64| {
+        if total == 0.0 {
+            0.0
+        } else {
+            100.0 * part / total
+        }
+    };
+    println!(
+        "{} of {} keys ({:.2}% of the weight) land in different partitions under {} and {} with {} partitions",
+        report.disagreeing_keys,
+        report.keys,
+        share(report.disagreeing_weight, report.weight),
+        report.left.name(),
+        report.right.name(),
+        report.partition_count
+    );
+    if report.disagreements.is_empty() {
+        return Ok(());
+    }
+    println!(
+        "{:>12} {:>12} {:>10}  key",
+        report.left.name(),
+        report.right.name(),
+        "weight"
+    );
+    for entry in &report.disagreements {
+        println!(
+            "{:>12} {:>12} {:>10}  {}",
+            entry.left_partition_id,
+            entry.right_partition_id,
+            format_weight(entry.weight),
+            entry.key
+        );
+    }
+    if report.disagreeing_keys > report.disagreements.len() as u64 {
+        println!(
+            "... and {} more",
+            report.disagreeing_keys - report.disagreements.len() as u64
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::get_partition_id;
+    use crate::kafka::{murmur2, to_positive};
+
+    #[test]
+    fn test_disagreements_between_amqp_and_kafka() {
+        let weights: HashMap<String, f64> =
+            (0..200).map(|i| (format!("order-{i}"), i as f64)).collect();
+        let report = crosscheck(&weights, Scheme::ServiceBus, Scheme::Kafka, 4);
+
+        let expected: Vec<&String> = weights
+            .keys()
+            .filter(|key| {
+                get_partition_id(4, key) as i32 != to_positive(murmur2(key.as_bytes())) % 4
+            })
+            .collect();
+        assert_eq!(report.disagreeing_keys, expected.len() as u64);
+        assert!(report.disagreeing_keys > 100);
+        assert!(report
+            .disagreements
+            .windows(2)
+            .all(|pair| pair[0].weight >= pair[1].weight));
+
+        let same = crosscheck(&weights, Scheme::Kafka, Scheme::Kafka, 4);
+        assert_eq!(same.disagreeing_keys, 0);
+    }
+}
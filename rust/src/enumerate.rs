@@ -0,0 +1,168 @@
+use clap::Args;
+use rayon::prelude::*;
+use serde::Serialize;
+use std::io::Write;
+
+use crate::keyspace::KeySpace;
+use crate::partitioner::{Partitioner, Scheme};
+use crate::print_json;
+
+/// Keys handed to the thread pool at once when streaming the keys of one partition.
+const BLOCK_SIZE: u64 = 1 << 20;
+
+#[derive(Args, Debug)]
+pub struct EnumerateArgs {
+    /// Key space, e.g. `TENANT-{0000..9999}`, `{1..10000000}` or `order-[A-Z]{2}-{000..999}`
+    key_space: KeySpace,
+
+    /// Number of partitions
+    #[clap(short = 'c', long, value_parser = clap::value_parser!(i16).range(1..))]
+    partition_count: i16,
+
+    /// Partitioning scheme that places the keys
+    #[clap(long, value_enum, default_value_t)]
+    scheme: Scheme,
+
+    /// Print the keys of this partition instead of the per-partition counts
+    #[clap(short = 'p', long)]
+    partition: Option<u16>,
+
+    /// Print JSON instead of a table
+    #[clap(long, conflicts_with = "partition")]
+    json: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PartitionCount {
+    pub partition_id: u16,
+    pub keys: u64,
+}
+
+pub fn run(args: &EnumerateArgs) -> Result<(), String> {
+    args.scheme.check_partition_count(args.partition_count)?;
+    let partitioner = args.scheme.partitioner(args.partition_count);
+    if let Some(partition) = args.partition {
+        if partition as i32 >= args.partition_count as i32 {
+            return Err(format!(
+                "partition {partition} does not exist with {} partitions",
+                args.partition_count
+            ));
+        }
+        let stdout = std::io::stdout();
+        let mut out = std::io::BufWriter::new(stdout.lock());
+        return for_each_key_in_partition(
+            &args.key_space,
+            partitioner.as_ref(),
+            partition,
+            |key| writeln!(out, "{key}").map_err(|e| e.to_string()),
+        );
+    }
+
+    let counts = count_keys(&args.key_space, partitioner.as_ref());
+    if args.json {
+        #[derive(Serialize)]
+        struct Report<'a> {
+            partition_count: i16,
+            total_keys: u64,
+            counts: &'a [PartitionCount],
+        }
+        print_json(&Report {
+            partition_count: args.partition_count,
+            total_keys: args.key_space.len(),
+            counts: &counts,
+        })
+    } else {
+        println!("{:>12} {:>12} {:>8}", "partition_id", "keys", "share");
+        for count in &counts {
+            println!(
+                "{:>12} {:>12} {:>7.3}%",
+                count.partition_id,
+                count.keys,
+                100.0 * count.keys as f64 / args.key_space.len() as f64
+            );
+        }
+        println!("{:>12} {:>12}", "total", args.key_space.len());
+        Ok(())
+    }
+}
+
+/// Counts the keys of every partition without materializing the key space.
+pub fn count_keys(space: &KeySpace, partitioner: &dyn Partitioner) -> Vec<PartitionCount> {
+    let partition_count = partitioner.partition_count() as usize;
+    let totals = (0..space.len())
+        .into_par_iter()
+        .fold(
+            || (vec![0u64; partition_count], String::new()),
+            |(mut counts, mut key), index| {
+                space.write_key(index, &mut key);
+                counts[partitioner.partition_of(&key) as usize] += 1;
+                (counts, key)
+            },
+        )
+        .map(|(counts, _)| counts)
+        .reduce(
+            || vec![0u64; partition_count],
+            |mut left, right| {
+                left.iter_mut().zip(right).for_each(|(l, r)| *l += r);
+                left
+            },
+        );
+
+    totals
+        .into_iter()
+        .enumerate()
+        .map(|(partition_id, keys)| PartitionCount {
+            partition_id: partition_id as u16,
+            keys,
+        })
+        .collect()
+}
+
+/// Calls `f` with the keys of one partition in key-space order, hashing block by block in parallel.
+pub fn for_each_key_in_partition(
+    space: &KeySpace,
+    partitioner: &dyn Partitioner,
+    partition: u16,
+    mut f: impl FnMut(&str) -> Result<(), String>,
+) -> Result<(), String> {
+    let mut start = 0;
+    while start < space.len() {
+        let end = u64::min(start + BLOCK_SIZE, space.len());
+        let keys: Vec<String> = (start..end)
+            .into_par_iter()
+            .filter_map(|index| {
+                let key = space.key(index);
+                (partitioner.partition_of(&key) == partition).then_some(key)
+            })
+            .collect();
+        for key in &keys {
+            f(key)?;
+        }
+        start = end;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::get_partition_id;
+
+    #[test]
+    fn test_counts_match_single_key_computation() -> Result<(), String> {
+        let space: KeySpace = "TENANT-{0000..1999}".parse()?;
+        let partitioner = Scheme::ServiceBus.partitioner(7);
+        let counts = count_keys(&space, partitioner.as_ref());
+        assert_eq!(counts.iter().map(|count| count.keys).sum::<u64>(), 2000);
+
+        let mut keys = Vec::new();
+        for_each_key_in_partition(&space, partitioner.as_ref(), 3, |key| {
+            keys.push(key.to_string());
+            Ok(())
+        })?;
+        assert_eq!(keys.len() as u64, counts[3].keys);
+        assert!(keys.iter().all(|key| get_partition_id(7, key) == 3));
+        assert!(keys.windows(2).all(|pair| pair[0] < pair[1]));
+        Ok(())
+    }
+}
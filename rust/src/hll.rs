@@ -0,0 +1,121 @@
+//! HyperLogLog sketches (Flajolet et al.) for counting distinct keys in fixed memory.
+//!
+//! Keys are hashed with lookup3 under a fixed seed of its own, so the hash bits do not
+//! depend on the logical partition of a key and sketches stay comparable across runs.
+//! A sketch with precision `p` keeps `2^p` one-byte registers and has a standard error of
+//! about `1.04 / sqrt(2^p)`; two sketches of the same precision merge by taking the larger
+//! value of every register.
+
+use serde::{Deserialize, Serialize};
+
+use crate::hash_seeded;
+
+pub const DEFAULT_PRECISION: u8 = 12;
+pub const PRECISIONS: std::ops::RangeInclusive<u8> = 4..=16;
+
+const SEED: (u32, u32) = (0x9e37_79b9, 0x7f4a_7c15);
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HyperLogLog {
+    precision: u8,
+    registers: Vec<u8>,
+}
+
+impl HyperLogLog {
+    pub fn new(precision: u8) -> Self {
+        assert!(PRECISIONS.contains(&precision));
+        HyperLogLog {
+            precision,
+            registers: vec![0; 1 << precision],
+        }
+    }
+
+    pub fn add(&mut self, key: &str) {
+        let (_, _, hash) = hash_seeded(key.as_bytes(), SEED.0, SEED.1);
+        let index = (hash >> (64 - self.precision)) as usize;
+        // The rank of the first set bit in the remaining bits, capped for an all-zero rest.
+        let rest = hash << self.precision;
+        let rank = u32::min(rest.leading_zeros(), 64 - self.precision as u32) + 1;
+        self.registers[index] = u8::max(self.registers[index], rank as u8);
+    }
+
+    pub fn merge(&mut self, other: &HyperLogLog) -> Result<(), String> {
+        if other.precision != self.precision {
+            return Err(format!(
+                "cannot merge a HyperLogLog of precision {} into one of precision {}",
+                other.precision, self.precision
+            ));
+        }
+        for (own, theirs) in self.registers.iter_mut().zip(&other.registers) {
+            *own = u8::max(*own, *theirs);
+        }
+        Ok(())
+    }
+
+    /// Estimated number of distinct keys added, with linear counting for small cardinalities.
+    pub fn estimate(&self) -> u64 {
+        let m = self.registers.len() as f64;
+        let alpha = match self.registers.len() {
+            16 => 0.673,
+            32 => 0.697,
+            64 => 0.709,
+            _ => 0.7213 / (1.0 + 1.079 / m),
+        };
+        let sum: f64 = self
+            .registers
+            .iter()
+            .map(|register| 2f64.powi(-(*register as i32)))
+            .sum();
+        let raw = alpha * m * m / sum;
+        let zeros = self
+            .registers
+            .iter()
+            .filter(|register| **register == 0)
+            .count();
+        let estimate = if raw <= 2.5 * m && zeros > 0 {
+            m * (m / zeros as f64).ln()
+        } else {
+            raw
+        };
+        estimate.round() as u64
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if !PRECISIONS.contains(&self.precision) || self.registers.len() != 1 << self.precision {
+            return Err(format!(
+                "HyperLogLog of precision {} has {} registers",
+                self.precision,
+                self.registers.len()
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_estimates_are_within_a_few_percent() -> Result<(), String> {
+        let (mut first, mut second) = (HyperLogLog::new(12), HyperLogLog::new(12));
+        for i in 0..60_000 {
+            first.add(&format!("session-{i}"));
+        }
+        // Half of the second day's keys were already seen on the first day.
+        for i in 30_000..90_000 {
+            second.add(&format!("session-{i}"));
+        }
+        let within = |estimate: u64, exact: f64| (estimate as f64 - exact).abs() / exact < 0.05;
+        assert!(within(first.estimate(), 60_000.0));
+
+        first.merge(&second)?;
+        assert!(within(first.estimate(), 90_000.0));
+        assert!(first.merge(&HyperLogLog::new(10)).is_err());
+
+        let mut small = HyperLogLog::new(12);
+        ["a", "b", "c", "a"].iter().for_each(|key| small.add(key));
+        assert_eq!(small.estimate(), 3);
+        Ok(())
+    }
+}
@@ -0,0 +1,95 @@
+//! The default partitioner of the Java Kafka client for records with a key:
+//! `toPositive(murmur2(keyBytes)) % numPartitions`.
+//!
+//! Kafka clients hash the serialized key bytes as they are, so unlike the Service Bus scheme
+//! the result is case sensitive.
+
+use crate::partitioner::Partitioner;
+
+/// `Utils.murmur2` results asserted by `UtilsTest.testMurmur2` of the Java client.
+pub const MURMUR2_VECTORS: &[(&[u8], i32)] = &[
+    (b"21", -973932308),
+    (b"foobar", -790332482),
+    (b"a-little-bit-long-string", -985981536),
+    (b"a-little-bit-longer-string", -1486304829),
+    (
+        b"lkjh234lh9fiuh90y23oiuhsafujhadof229phr9h19h89h8",
+        -58897971,
+    ),
+    (b"abc", 479470107),
+];
+
+/// MurmurHash2 as implemented by `org.apache.kafka.common.utils.Utils.murmur2`.
+pub fn murmur2(data: &[u8]) -> i32 {
+    const SEED: u32 = 0x9747_b28c;
+    const M: u32 = 0x5bd1_e995;
+    const R: u32 = 24;
+
+    let mut h = SEED ^ data.len() as u32;
+    let mut chunks = data.chunks_exact(4);
+    for chunk in &mut chunks {
+        let mut k = u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+        k = k.wrapping_mul(M);
+        k ^= k >> R;
+        k = k.wrapping_mul(M);
+        h = h.wrapping_mul(M);
+        h ^= k;
+    }
+    let tail = chunks.remainder();
+    if !tail.is_empty() {
+        for (i, byte) in tail.iter().enumerate().rev() {
+            h ^= (*byte as u32) << (8 * i);
+        }
+        h = h.wrapping_mul(M);
+    }
+    h ^= h >> 13;
+    h = h.wrapping_mul(M);
+    h ^= h >> 15;
+    h as i32
+}
+
+/// `Utils.toPositive`: clears the sign bit rather than taking the absolute value.
+pub fn to_positive(n: i32) -> i32 {
+    n & 0x7fff_ffff
+}
+
+#[derive(Debug, Clone)]
+pub struct Kafka {
+    partition_count: i16,
+}
+
+impl Kafka {
+    pub fn new(partition_count: i16) -> Self {
+        Kafka { partition_count }
+    }
+}
+
+impl Partitioner for Kafka {
+    fn partition_count(&self) -> i16 {
+        self.partition_count
+    }
+
+    fn partition(&self, key: &[u8]) -> u16 {
+        (to_positive(murmur2(key)) % self.partition_count as i32) as u16
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_murmur2_matches_java_client() {
+        for (data, expected) in MURMUR2_VECTORS {
+            assert_eq!(
+                murmur2(data),
+                *expected,
+                "{}",
+                String::from_utf8_lossy(data)
+            );
+        }
+        // -790332482 & 0x7fffffff = 1357151166, which is 30 modulo 32
+        assert_eq!(Kafka::new(32).partition(b"foobar"), 30);
+        assert_ne!(murmur2(b"abc"), murmur2(b"ABC"));
+    }
+}
@@ -0,0 +1,215 @@
+//! Compact descriptions of bounded key spaces, e.g. `TENANT-{0000..9999}` or `order-[A-Z]{2}-{1..500}`.
+//!
+//! A spec is a sequence of segments:
+//!
+//! - literal text, where `\` escapes the next character;
+//! - `{start..end}`: every number in the inclusive range, zero padded to the width of the
+//!   longer bound when one of the bounds has a leading zero;
+//! - `[...]`: one character out of a class like `[A-Za-z0-9_]`, optionally repeated with `{n}`.
+//!
+//! Keys are numbered in lexicographic segment order, the last segment varying fastest.
+
+use std::fmt::Write;
+use std::str::FromStr;
+
+const TOO_LARGE: &str = "key space has more than 2^64 keys";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Segment {
+    Literal(String),
+    Numeric { start: u64, end: u64, width: usize },
+    Class(Vec<char>),
+}
+
+impl Segment {
+    /// Number of values of the segment; `None` when a full `u64` range has 2^64 of them.
+    fn radix(&self) -> Option<u64> {
+        match self {
+            Segment::Literal(_) => Some(1),
+            Segment::Numeric { start, end, .. } => (end - start).checked_add(1),
+            Segment::Class(chars) => Some(chars.len() as u64),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeySpace {
+    segments: Vec<Segment>,
+    /// Number of keys covered by the segments after each segment
+    strides: Vec<u64>,
+    len: u64,
+}
+
+impl KeySpace {
+    pub fn new(segments: Vec<Segment>) -> Result<Self, String> {
+        let mut strides = vec![0; segments.len()];
+        let mut len = 1u64;
+        for (index, segment) in segments.iter().enumerate().rev() {
+            strides[index] = len;
+            len = len
+                .checked_mul(segment.radix().ok_or(TOO_LARGE)?)
+                .ok_or(TOO_LARGE)?;
+        }
+        Ok(KeySpace {
+            segments,
+            strides,
+            len,
+        })
+    }
+
+    /// Number of keys in the space.
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Replaces the contents of `key` with the key at `index` (which must be below `len()`).
+    pub fn write_key(&self, index: u64, key: &mut String) {
+        key.clear();
+        for (segment, stride) in self.segments.iter().zip(&self.strides) {
+            // `new` rejects segments without a radix.
+            let digit = (index / stride) % segment.radix().unwrap();
+            match segment {
+                Segment::Literal(text) => key.push_str(text),
+                Segment::Numeric { start, width, .. } => {
+                    write!(key, "{:0width$}", start + digit, width = *width).unwrap()
+                }
+                Segment::Class(chars) => key.push(chars[digit as usize]),
+            }
+        }
+    }
+
+    pub fn key(&self, index: u64) -> String {
+        let mut key = String::new();
+        self.write_key(index, &mut key);
+        key
+    }
+}
+
+impl FromStr for KeySpace {
+    type Err = String;
+
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        let mut segments = Vec::new();
+        let mut literal = String::new();
+        let mut chars = spec.chars().peekable();
+        while let Some(ch) = chars.next() {
+            match ch {
+                '\\' => literal.push(chars.next().ok_or("dangling `\\` at end of key space")?),
+                '{' | '[' => {
+                    if !literal.is_empty() {
+                        segments.push(Segment::Literal(std::mem::take(&mut literal)));
+                    }
+                    let close = if ch == '{' { '}' } else { ']' };
+                    let body = take_until(&mut chars, close)?;
+                    if ch == '{' {
+                        segments.push(parse_numeric(&body)?);
+                        continue;
+                    }
+                    let class = parse_class(&body)?;
+                    let mut repeat = 1;
+                    if chars.peek() == Some(&'{') {
+                        chars.next();
+                        let count = take_until(&mut chars, '}')?;
+                        repeat = count
+                            .parse()
+                            .map_err(|_| format!("invalid repetition {{{count}}}"))?;
+                    }
+                    segments.extend(std::iter::repeat_n(Segment::Class(class), repeat));
+                }
+                _ => literal.push(ch),
+            }
+        }
+        if !literal.is_empty() {
+            segments.push(Segment::Literal(literal));
+        }
+        KeySpace::new(segments)
+    }
+}
+
+fn take_until(chars: &mut impl Iterator<Item = char>, close: char) -> Result<String, String> {
+    let mut body = String::new();
+    for ch in chars.by_ref() {
+        if ch == close {
+            return Ok(body);
+        }
+        body.push(ch);
+    }
+    Err(format!("missing `{close}` in key space"))
+}
+
+fn parse_numeric(body: &str) -> Result<Segment, String> {
+    let (start_text, end_text) = body
+        .split_once("..")
+        .ok_or_else(|| format!("expected {{start..end}}, got {{{body}}}"))?;
+    let parse = |text: &str| {
+        text.parse::<u64>()
+            .map_err(|_| format!("invalid number {text:?} in {{{body}}}"))
+    };
+    let (start, end) = (parse(start_text)?, parse(end_text)?);
+    if start > end {
+        return Err(format!("empty range {{{body}}}"));
+    }
+    let padded = |text: &str| text.len() > 1 && text.starts_with('0');
+    let width = if padded(start_text) || padded(end_text) {
+        usize::max(start_text.len(), end_text.len())
+    } else {
+        0
+    };
+    Ok(Segment::Numeric { start, end, width })
+}
+
+pub fn parse_class(body: &str) -> Result<Vec<char>, String> {
+    let body: Vec<char> = body.chars().collect();
+    let mut class = Vec::new();
+    let mut i = 0;
+    while i < body.len() {
+        if i + 2 < body.len() && body[i + 1] == '-' {
+            let (from, to) = (body[i], body[i + 2]);
+            if from > to {
+                return Err(format!("invalid character range {from}-{to}"));
+            }
+            class.extend(from..=to);
+            i += 3;
+        } else {
+            class.push(body[i]);
+            i += 1;
+        }
+    }
+    class.sort_unstable();
+    class.dedup();
+    if class.is_empty() {
+        return Err("empty character class []".to_string());
+    }
+    Ok(class)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_space_enumerates_in_order() -> Result<(), String> {
+        let space: KeySpace = "T-{08..10}-[a-b]{2}".parse()?;
+        assert_eq!(space.len(), 12);
+        assert_eq!(space.key(0), "T-08-aa");
+        assert_eq!(space.key(1), "T-08-ab");
+        assert_eq!(space.key(4), "T-09-aa");
+        assert_eq!(space.key(11), "T-10-bb");
+
+        let unpadded: KeySpace = "{1..10000000}".parse()?;
+        assert_eq!(unpadded.len(), 10_000_000);
+        assert_eq!(unpadded.key(41), "42");
+        assert_eq!(r"\{x\}".parse::<KeySpace>()?.key(0), "{x}");
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_specs_are_rejected() {
+        assert!("{5..1}".parse::<KeySpace>().is_err());
+        assert!("{1..}".parse::<KeySpace>().is_err());
+        assert!("[a-z".parse::<KeySpace>().is_err());
+        assert!("[0-9]{30}".parse::<KeySpace>().is_err());
+        assert!("{0..18446744073709551615}".parse::<KeySpace>().is_err());
+        assert!("{1..18446744073709551615}".parse::<KeySpace>().is_ok());
+    }
+}
@@ -0,0 +1,152 @@
+use clap::{Args, ValueEnum};
+use rayon::prelude::*;
+use serde::Serialize;
+use std::path::PathBuf;
+
+use crate::partitioner::Scheme;
+use crate::print_json;
+use crate::recommend::Sample;
+use crate::records::{read_key_files, RecordFields};
+
+#[derive(Args, Debug)]
+pub struct MigrateArgs {
+    /// Sample key files with one record per line (reads stdin when omitted)
+    files: Vec<PathBuf>,
+
+    /// Partition count today
+    #[clap(long, value_parser = clap::value_parser!(i16).range(1..))]
+    from: i16,
+
+    /// Partition count after the migration
+    #[clap(long, value_parser = clap::value_parser!(i16).range(1..))]
+    to: i16,
+
+    /// Schemes to compare (all when omitted)
+    #[clap(long, value_enum, value_delimiter = ',')]
+    schemes: Vec<Scheme>,
+
+    #[command(flatten)]
+    fields: RecordFields,
+
+    /// Print JSON instead of a Markdown table
+    #[clap(long)]
+    json: bool,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+pub struct Migration {
+    pub scheme: Scheme,
+    pub keys_moved: u64,
+    pub key_share_moved: f64,
+    /// Share of the load (weight) whose keys move
+    pub load_moved: f64,
+    /// Load of the busiest partition divided by the mean after the migration
+    pub max_to_mean: f64,
+}
+
+/// Share of a balanced key set that has to move at the very least: the keys of the new
+/// partitions when growing, the keys of the removed ones when shrinking.
+pub fn minimum_share_moved(from: i16, to: i16) -> f64 {
+    (from as f64 - to as f64).abs() / from.max(to) as f64
+}
+
+pub fn migrate(sample: &Sample, schemes: &[Scheme], from: i16, to: i16) -> Vec<Migration> {
+    schemes
+        .par_iter()
+        .map(|scheme| {
+            let current = sample.place(*scheme, from);
+            let candidate = sample.evaluate(*scheme, to, Some(&current), None);
+            let keys_moved = candidate.keys_moved.unwrap_or(0);
+            Migration {
+                scheme: *scheme,
+                keys_moved,
+                key_share_moved: keys_moved as f64 / sample.len().max(1) as f64,
+                load_moved: candidate.load_moved.unwrap_or(0.0),
+                max_to_mean: candidate.max_to_mean,
+            }
+        })
+        .collect()
+}
+
+pub fn run(args: &MigrateArgs) -> Result<(), String> {
+    let (weights, skipped_lines) = read_key_files(&args.files, &args.fields)?;
+    let sample = Sample::new(weights, skipped_lines);
+    if sample.len() == 0 {
+        return Err("the sample has no keys".to_string());
+    }
+    let fits = |scheme: &Scheme| {
+        scheme
+            .check_partition_count(args.from)
+            .and_then(|()| scheme.check_partition_count(args.to))
+    };
+    let schemes = if args.schemes.is_empty() {
+        // Every scheme that can have both partition counts
+        Scheme::value_variants()
+            .iter()
+            .copied()
+            .filter(|scheme| fits(scheme).is_ok())
+            .collect()
+    } else {
+        for scheme in &args.schemes {
+            fits(scheme)?;
+        }
+        args.schemes.clone()
+    };
+
+    let migrations = migrate(&sample, &schemes, args.from, args.to);
+    if args.json {
+        return print_json(&migrations);
+    }
+    println!(
+        "{} distinct keys, {} lines skipped, {} → {} partitions, at least {:.1}% of the keys move",
+        sample.len(),
+        sample.skipped_lines,
+        args.from,
+        args.to,
+        100.0 * minimum_share_moved(args.from, args.to)
+    );
+    println!();
+    println!("| scheme | keys moved | keys moved % | load moved | max/mean after |");
+    println!("|---|---:|---:|---:|---:|");
+    for migration in &migrations {
+        println!(
+            "| {} | {} | {:.1}% | {:.1}% | {:.3} |",
+            migration.scheme.name(),
+            migration.keys_moved,
+            100.0 * migration.key_share_moved,
+            100.0 * migration.load_moved,
+            migration.max_to_mean
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_consistent_schemes_move_fewer_keys() {
+        let weights: HashMap<String, f64> =
+            (0..4000).map(|i| (format!("tenant-{i}"), 1.0)).collect();
+        let sample = Sample::new(weights, 0);
+        let migrations = migrate(&sample, Scheme::value_variants(), 16, 20);
+        let moved = |scheme: Scheme| {
+            migrations
+                .iter()
+                .find(|migration| migration.scheme == scheme)
+                .unwrap()
+                .key_share_moved
+        };
+
+        // Contiguous ranges and modulo schemes reshuffle most keys, the consistent schemes
+        // move close to the 20% minimum.
+        assert_eq!(minimum_share_moved(16, 20), 0.2);
+        assert!(moved(Scheme::ServiceBus) > 0.5);
+        assert!(moved(Scheme::Kafka) > 0.5);
+        for scheme in [Scheme::Jump, Scheme::Rendezvous] {
+            assert!((0.17..0.23).contains(&moved(scheme)), "{scheme:?}");
+        }
+    }
+}
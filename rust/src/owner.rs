@@ -0,0 +1,406 @@
+use clap::Args;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::analyze::format_weight;
+use crate::partitioner::Scheme;
+use crate::print_json;
+use crate::records::{read_key_files, RecordFields};
+use crate::simulate::parse_time;
+use crate::{to_logical_encoded, KeyEncoding};
+
+#[derive(Args, Debug)]
+#[clap(group = clap::ArgGroup::new("question").required(true).args(["keys", "consumer"]))]
+pub struct OwnerArgs {
+    /// Keys to look up
+    keys: Vec<String>,
+
+    /// Ownership map: the `processor --store` file or `processor --json` output, a JSON array
+    /// of ownership records (`partitionId`/`ownerIdentifier`), a JSON object of partition to
+    /// consumer, or `partition,consumer` CSV lines
+    #[clap(long)]
+    ownership: PathBuf,
+
+    /// Seconds after which an ownership record that was not renewed counts as unowned
+    #[clap(long, default_value_t = 30)]
+    expiration: u64,
+
+    /// Time at which records expire, on the records' clock (simulation or epoch seconds,
+    /// or RFC 3339); default: the latest record
+    #[clap(long, value_parser = parse_now)]
+    now: Option<f64>,
+
+    /// Number of partitions
+    #[clap(short = 'c', long, value_parser = clap::value_parser!(i16).range(1..))]
+    partition_count: i16,
+
+    /// Partitioning scheme that places the keys
+    #[clap(long, value_enum, default_value_t)]
+    scheme: Scheme,
+
+    /// List the partitions and sampled keys of this consumer instead
+    #[clap(long)]
+    consumer: Option<String>,
+
+    /// Sample key files for --consumer (reads stdin when omitted)
+    #[clap(long = "sample", requires = "consumer")]
+    sample_files: Vec<PathBuf>,
+
+    #[command(flatten)]
+    fields: RecordFields,
+
+    /// Number of sampled keys to list, heaviest first
+    #[clap(short = 'n', long, default_value_t = 20)]
+    show: usize,
+
+    /// Print JSON instead of a table
+    #[clap(long)]
+    json: bool,
+}
+
+/// Partition to consumer; partitions that nobody owns are missing.
+pub type OwnershipMap = BTreeMap<u16, String>;
+
+/// When ownership records stop counting, as in the processor's load balancing.
+#[derive(Debug, Clone, Copy)]
+pub struct Expiry {
+    pub expiration: f64,
+    /// `None` takes the latest record time
+    pub now: Option<f64>,
+}
+
+#[derive(Deserialize)]
+struct Record {
+    #[serde(alias = "partitionId")]
+    partition_id: Value,
+    #[serde(alias = "ownerIdentifier", alias = "owner")]
+    owner_id: String,
+    #[serde(default, alias = "lastModifiedTime")]
+    last_modified: Option<Value>,
+}
+
+fn parse_now(text: &str) -> Result<f64, String> {
+    parse_time(text).ok_or_else(|| format!("{text:?} is neither seconds nor an RFC 3339 time"))
+}
+
+fn record_time(value: &Value) -> Result<f64, String> {
+    match value {
+        Value::Number(number) => number.as_f64(),
+        Value::String(text) => parse_time(text),
+        _ => None,
+    }
+    .ok_or_else(|| format!("{value} is not a modification time"))
+}
+
+fn partition_id(value: &Value) -> Result<u16, String> {
+    match value {
+        Value::Number(number) => number.as_u64().and_then(|id| u16::try_from(id).ok()),
+        Value::String(text) => text.trim().parse().ok(),
+        _ => None,
+    }
+    .ok_or_else(|| format!("{value} is not a partition id"))
+}
+
+/// Parses any of the ownership map formats; released records (empty owner) and records not
+/// renewed within `expiry.expiration` of `expiry.now` are skipped.
+pub fn parse_ownership(text: &str, expiry: Expiry) -> Result<OwnershipMap, String> {
+    let mut map = OwnershipMap::new();
+    let mut insert = |partition: u16, owner: &str| -> Result<(), String> {
+        let owner = owner.trim();
+        if owner.is_empty() {
+            return Ok(());
+        }
+        match map.insert(partition, owner.to_string()) {
+            Some(other) if other != owner => Err(format!(
+                "partition {partition} is owned by both {other} and {owner}"
+            )),
+            _ => Ok(()),
+        }
+    };
+
+    match text.trim_start().chars().next() {
+        Some('[') => {
+            let records: Vec<Record> = serde_json::from_str(text).map_err(|e| e.to_string())?;
+            let times = records
+                .iter()
+                .map(|record| record.last_modified.as_ref().map(record_time).transpose())
+                .collect::<Result<Vec<_>, _>>()?;
+            let now = expiry
+                .now
+                .or_else(|| times.iter().flatten().copied().reduce(f64::max));
+            for (record, time) in records.iter().zip(&times) {
+                if let (Some(time), Some(now)) = (time, now) {
+                    if now >= time + expiry.expiration {
+                        continue;
+                    }
+                }
+                insert(partition_id(&record.partition_id)?, &record.owner_id)?;
+            }
+        }
+        Some('{') => {
+            let value: Value = serde_json::from_str(text).map_err(|e| e.to_string())?;
+            // `processor --json`: the owner of every partition, `null` when unowned
+            if let Some(Value::Array(owners)) = value.get("owners") {
+                for (partition, owner) in owners.iter().enumerate() {
+                    match owner {
+                        Value::String(owner) => insert(partition as u16, owner)?,
+                        Value::Null => {}
+                        other => return Err(format!("{other} is not a consumer")),
+                    }
+                }
+                return Ok(map);
+            }
+            let object: BTreeMap<String, String> =
+                serde_json::from_value(value).map_err(|e| e.to_string())?;
+            for (partition, owner) in &object {
+                insert(partition_id(&Value::String(partition.clone()))?, owner)?;
+            }
+        }
+        _ => {
+            for (number, line) in text.lines().enumerate() {
+                let Some((partition, owner)) = line.split_once(',') else {
+                    if line.trim().is_empty() {
+                        continue;
+                    }
+                    return Err(format!(
+                        "line {}: expected `partition,consumer`",
+                        number + 1
+                    ));
+                };
+                match partition.trim().parse() {
+                    Ok(partition) => insert(partition, owner)?,
+                    // A header line
+                    Err(_) if number == 0 => {}
+                    Err(_) => {
+                        return Err(format!(
+                            "line {}: {partition:?} is not a partition id",
+                            number + 1
+                        ))
+                    }
+                }
+            }
+        }
+    }
+    Ok(map)
+}
+
+pub fn load_ownership(
+    path: &Path,
+    partition_count: i16,
+    expiry: Expiry,
+) -> Result<OwnershipMap, String> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| format!("cannot read {}: {e}", path.display()))?;
+    let map = parse_ownership(&text, expiry).map_err(|e| format!("{}: {e}", path.display()))?;
+    if let Some(partition) = map
+        .keys()
+        .find(|partition| i32::from(**partition) >= i32::from(partition_count))
+    {
+        return Err(format!(
+            "{}: partition {partition} does not exist with {partition_count} partitions",
+            path.display()
+        ));
+    }
+    Ok(map)
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+pub struct KeyOwner {
+    pub key: String,
+    /// `None` when nobody owns the partition
+    pub consumer: Option<String>,
+    pub partition_id: u16,
+    /// Only for the Service Bus scheme
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub logical_partition: Option<i32>,
+}
+
+pub fn locate(
+    keys: &[String],
+    ownership: &OwnershipMap,
+    scheme: Scheme,
+    partition_count: i16,
+) -> Vec<KeyOwner> {
+    let partitioner = scheme.partitioner(partition_count);
+    keys.iter()
+        .map(|key| {
+            let partition_id = partitioner.partition_of(key);
+            KeyOwner {
+                key: key.clone(),
+                consumer: ownership.get(&partition_id).cloned(),
+                partition_id,
+                logical_partition: (scheme == Scheme::ServiceBus)
+                    .then(|| to_logical_encoded(key, KeyEncoding::Utf8)),
+            }
+        })
+        .collect()
+}
+
+#[derive(Debug, Serialize)]
+pub struct ConsumerKeys {
+    pub consumer: String,
+    pub partitions: Vec<u16>,
+    pub sampled_keys: u64,
+    pub sampled_weight: f64,
+    pub skipped_lines: u64,
+    /// The heaviest sampled keys of the consumer
+    pub keys: Vec<KeyOwner>,
+}
+
+pub fn run(args: &OwnerArgs) -> Result<(), String> {
+    args.scheme.check_partition_count(args.partition_count)?;
+    let expiry = Expiry {
+        expiration: args.expiration as f64,
+        now: args.now,
+    };
+    let ownership = load_ownership(&args.ownership, args.partition_count, expiry)?;
+
+    let Some(consumer) = &args.consumer else {
+        let owners = locate(&args.keys, &ownership, args.scheme, args.partition_count);
+        if args.json {
+            return print_json(&owners);
+        }
+        println!(
+            "{:>16} {:>9} {:>7}  key",
+            "consumer", "partition", "logical"
+        );
+        for owner in &owners {
+            println!(
+                "{:>16} {:>9} {:>7}  {}",
+                owner.consumer.as_deref().unwrap_or("-"),
+                owner.partition_id,
+                owner
+                    .logical_partition
+                    .map_or("-".to_string(), |logical| logical.to_string()),
+                owner.key
+            );
+        }
+        return Ok(());
+    };
+
+    let partitions: Vec<u16> = ownership
+        .iter()
+        .filter(|(_, owner)| *owner == consumer)
+        .map(|(partition, _)| *partition)
+        .collect();
+    if partitions.is_empty() {
+        return Err(format!("{consumer} owns no partition"));
+    }
+    let (weights, skipped_lines) = read_key_files(&args.sample_files, &args.fields)?;
+    let mut sampled: Vec<(&String, f64)> =
+        weights.iter().map(|(key, weight)| (key, *weight)).collect();
+    sampled.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+    let keys: Vec<String> = sampled.iter().map(|(key, _)| (*key).clone()).collect();
+    let mut owned: Vec<(KeyOwner, f64)> =
+        locate(&keys, &ownership, args.scheme, args.partition_count)
+            .into_iter()
+            .zip(sampled.iter().map(|(_, weight)| *weight))
+            .filter(|(owner, _)| owner.consumer.as_ref() == Some(consumer))
+            .collect();
+    let report = ConsumerKeys {
+        consumer: consumer.clone(),
+        partitions,
+        sampled_keys: owned.len() as u64,
+        sampled_weight: owned.iter().map(|(_, weight)| weight).sum(),
+        skipped_lines,
+        keys: {
+            owned.truncate(args.show);
+            owned.into_iter().map(|(owner, _)| owner).collect()
+        },
+    };
+    if args.json {
+        return print_json(&report);
+    }
+
+    let partitions: Vec<String> = report.partitions.iter().map(u16::to_string).collect();
+    println!(
+        "{} owns partitions {} with {} of {} sampled keys (weight {})",
+        report.consumer,
+        partitions.join(","),
+        report.sampled_keys,
+        weights.len(),
+        format_weight(report.sampled_weight)
+    );
+    for owner in &report.keys {
+        println!("{:>9}  {}", owner.partition_id, owner.key);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::get_partition_id;
+
+    const EXPIRY: Expiry = Expiry {
+        expiration: 30.0,
+        now: None,
+    };
+
+    #[test]
+    fn test_ownership_formats_and_lookup() -> Result<(), String> {
+        let simulator = r#"[
+            {"partition_id": 0, "owner_id": "pod-a", "last_modified": 10, "version": 3},
+            {"partition_id": 1, "owner_id": "", "last_modified": 10, "version": 4}
+        ]"#;
+        let sdk = r#"[{"partitionId": "0", "ownerIdentifier": "pod-a"}]"#;
+        let object = r#"{"0": "pod-a"}"#;
+        let processor_json = r#"{"handovers": [], "owners": ["pod-a", null], "settled_after": 0}"#;
+        let csv = "partition,consumer\n0,pod-a\n";
+        for text in [simulator, sdk, object, processor_json, csv] {
+            assert_eq!(
+                parse_ownership(text, EXPIRY)?,
+                OwnershipMap::from([(0, "pod-a".to_string())]),
+                "{text}"
+            );
+        }
+        assert!(parse_ownership("0,pod-a\n0,pod-b", EXPIRY).is_err());
+
+        let path = std::env::temp_dir().join(format!("owners-{}.csv", std::process::id()));
+        std::fs::write(&path, "0,pod-a\n40000,pod-b\n").map_err(|e| e.to_string())?;
+        let result = load_ownership(&path, 2, EXPIRY);
+        std::fs::remove_file(&path).map_err(|e| e.to_string())?;
+        assert!(result
+            .unwrap_err()
+            .contains("partition 40000 does not exist"));
+
+        let ownership = parse_ownership("0,pod-a\n1,pod-b", EXPIRY)?;
+        let keys: Vec<String> = (0..20).map(|i| format!("customer-{i}")).collect();
+        for owner in locate(&keys, &ownership, Scheme::ServiceBus, 2) {
+            assert_eq!(owner.partition_id, get_partition_id(2, &owner.key));
+            let expected = if owner.partition_id == 0 {
+                "pod-a"
+            } else {
+                "pod-b"
+            };
+            assert_eq!(owner.consumer.as_deref(), Some(expected));
+            assert!(owner.logical_partition.is_some());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_expired_records_are_unowned() -> Result<(), String> {
+        // pod-c crashed: its record was last renewed 60s before the others.
+        let simulator = r#"[
+            {"partition_id": 0, "owner_id": "pod-a", "last_modified": 100, "version": 9},
+            {"partition_id": 1, "owner_id": "pod-c", "last_modified": 40, "version": 2}
+        ]"#;
+        let live = OwnershipMap::from([(0, "pod-a".to_string())]);
+        assert_eq!(parse_ownership(simulator, EXPIRY)?, live);
+        let earlier = Expiry {
+            now: Some(50.0),
+            ..EXPIRY
+        };
+        assert_eq!(parse_ownership(simulator, earlier)?.len(), 2);
+
+        let sdk = r#"[
+            {"partitionId": "0", "ownerIdentifier": "pod-a", "lastModifiedTime": "2024-05-01T12:00:00Z"},
+            {"partitionId": "1", "ownerIdentifier": "pod-c", "lastModifiedTime": "2024-05-01T11:58:00Z"}
+        ]"#;
+        assert_eq!(parse_ownership(sdk, EXPIRY)?, live);
+        Ok(())
+    }
+}
@@ -0,0 +1,147 @@
+//! Partitioning schemes: how a broker turns key bytes and a partition count into a partition.
+//!
+//! A [`Scheme`] builds a [`Partitioner`] for one partition count, so per-count state such as
+//! the Service Bus range table is computed once and shared by every key.
+
+use serde::{Deserialize, Serialize};
+
+use crate::consistent::{JumpHash, Rendezvous};
+use crate::kafka::Kafka;
+use crate::ranges::range_table;
+use crate::redis::{slot_map, RedisCluster, SLOT_COUNT};
+use crate::{to_logical_encoded, to_partition_id, KeyEncoding};
+
+pub trait Partitioner: Send + Sync {
+    fn partition_count(&self) -> i16;
+
+    /// Partition of a key, given as the bytes the client sends.
+    fn partition(&self, key: &[u8]) -> u16;
+
+    /// Partition of a string key.
+    fn partition_of(&self, key: &str) -> u16 {
+        self.partition(key.as_bytes())
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Scheme {
+    /// Service Bus / Event Hubs AMQP: lookup3 of the upper-cased key, split into 32767
+    /// logical partitions that are spread over contiguous ranges
+    #[default]
+    ServiceBus,
+    /// Kafka clients' default partitioner: murmur2 of the key bytes modulo the partition count
+    Kafka,
+    /// Redis Cluster: CRC16 of the key or its {hash tag} modulo 16384 slots, spread over the
+    /// nodes in contiguous slot ranges as `redis-cli --cluster create` assigns them, or as a
+    /// `--slot-map` with that many nodes assigns them
+    Redis,
+    /// Jump Consistent Hash of the lookup3 key hash
+    Jump,
+    /// Rendezvous (highest random weight) hashing with one lookup3 seed per partition
+    Rendezvous,
+}
+
+impl Scheme {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Scheme::ServiceBus => "service-bus",
+            Scheme::Kafka => "kafka",
+            Scheme::Redis => "redis",
+            Scheme::Jump => "jump",
+            Scheme::Rendezvous => "rendezvous",
+        }
+    }
+
+    /// Rejects partition counts the scheme cannot have: a Redis cluster has at most one primary
+    /// per hash slot.
+    pub fn check_partition_count(&self, partition_count: i16) -> Result<(), String> {
+        if *self == Scheme::Redis && partition_count as i32 > SLOT_COUNT as i32 {
+            return Err(format!(
+                "a Redis cluster has at most {SLOT_COUNT} nodes, not {partition_count}"
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn partitioner(&self, partition_count: i16) -> Box<dyn Partitioner> {
+        match self {
+            Scheme::ServiceBus => Box::new(ServiceBus::new(partition_count, KeyEncoding::Utf8)),
+            Scheme::Kafka => Box::new(Kafka::new(partition_count)),
+            Scheme::Redis => Box::new(RedisCluster::new(slot_map(partition_count))),
+            Scheme::Jump => Box::new(JumpHash::new(partition_count)),
+            Scheme::Rendezvous => Box::new(Rendezvous::new(partition_count)),
+        }
+    }
+}
+
+/// The Service Bus lookup3/range scheme.
+#[derive(Debug, Clone)]
+pub struct ServiceBus {
+    ranges: Vec<i32>,
+    encoding: KeyEncoding,
+}
+
+impl ServiceBus {
+    pub fn new(partition_count: i16, encoding: KeyEncoding) -> Self {
+        Self::with_ranges(range_table(partition_count), encoding)
+    }
+
+    /// The scheme over an explicit range table of inclusive upper logical partitions.
+    pub fn with_ranges(ranges: Vec<i32>, encoding: KeyEncoding) -> Self {
+        ServiceBus { ranges, encoding }
+    }
+}
+
+impl Partitioner for ServiceBus {
+    fn partition_count(&self) -> i16 {
+        self.ranges.len() as i16
+    }
+
+    fn partition(&self, key: &[u8]) -> u16 {
+        self.partition_of(&String::from_utf8_lossy(key))
+    }
+
+    fn partition_of(&self, key: &str) -> u16 {
+        to_partition_id(&self.ranges, to_logical_encoded(key, self.encoding))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::verify::{parse_vectors, SERVICE_BUS_VECTORS};
+
+    #[test]
+    fn test_service_bus_scheme_matches_vectors() -> Result<(), String> {
+        for vector in parse_vectors(SERVICE_BUS_VECTORS)? {
+            let partitioner = Scheme::ServiceBus.partitioner(vector.partition_count);
+            assert_eq!(partitioner.partition_count(), vector.partition_count);
+            assert_eq!(
+                partitioner.partition(vector.key.as_bytes()),
+                vector.expected_partition_id,
+                "{} with {} partitions",
+                vector.key,
+                vector.partition_count
+            );
+        }
+
+        // With the first of two partitions split in half, keys move up by one partition.
+        let split = ServiceBus::with_ranges(vec![8191, 16383, 32766], KeyEncoding::Utf8);
+        let even = Scheme::ServiceBus.partitioner(2);
+        for key in ["a", "b", "c", "d", "e", "f"] {
+            let logical = to_logical_encoded(key, KeyEncoding::Utf8);
+            let expected = if logical <= 8191 {
+                0
+            } else {
+                even.partition_of(key) + 1
+            };
+            assert_eq!(split.partition_of(key), expected, "{key}");
+        }
+
+        assert!(Scheme::Redis.check_partition_count(16384).is_ok());
+        assert!(Scheme::Redis.check_partition_count(16385).is_err());
+        assert!(Scheme::Kafka.check_partition_count(i16::MAX).is_ok());
+        Ok(())
+    }
+}
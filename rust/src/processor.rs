@@ -0,0 +1,639 @@
+//! Event processor load balancing: consumers that claim partitions through an ownership
+//! store, as the Event Hubs / Service Bus processor clients do.
+//!
+//! Every consumer runs a load-balancing cycle when it joins and then every `interval`:
+//! it lists the ownership records, treats records older than `expiration` (and released
+//! ones) as unowned, renews its own records, and claims a partition while it owns fewer
+//! than its share. The share is `partitions / owners`, and `partitions % owners` of the owners
+//! may hold one more. A claim takes a random unowned partition first, otherwise steals from
+//! an owner above the maximum, otherwise - while below the minimum - from an owner at the
+//! maximum. Claims are optimistic: a record only changes if its version is still the one
+//! that was listed.
+
+use clap::{Args, ValueEnum};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+use crate::analyze::format_weight;
+use crate::partitioner::Scheme;
+use crate::print_json;
+use crate::records::{read_key_files, RecordFields};
+
+/// The simulation steps through every second, so it covers at most a week.
+const MAX_UNTIL: u64 = 7 * 24 * 60 * 60;
+
+#[derive(Args, Debug)]
+pub struct ProcessorArgs {
+    /// Timeline of `<seconds> join|leave|crash <consumer>` lines; `leave` releases the
+    /// consumer's partitions, `crash` leaves them to expire
+    events: PathBuf,
+
+    /// Number of partitions
+    #[clap(short = 'c', long, value_parser = clap::value_parser!(i16).range(1..))]
+    partition_count: i16,
+
+    /// Claim one partition per cycle (balanced) or until the share is reached (greedy)
+    #[clap(long, value_enum, default_value_t)]
+    strategy: Strategy,
+
+    /// Seconds between the load-balancing cycles of a consumer
+    #[clap(long, default_value_t = 10)]
+    interval: u64,
+
+    /// Seconds after which an ownership that was not renewed counts as unowned
+    #[clap(long, default_value_t = 30)]
+    expiration: u64,
+
+    /// Seconds to simulate, at most a week (default: one minute after the last event)
+    #[clap(long)]
+    until: Option<u64>,
+
+    /// Ownership store file, rewritten on every claim; holds the final ownership afterwards
+    #[clap(long)]
+    store: Option<PathBuf>,
+
+    /// Seed of the random choices between equally good partitions
+    #[clap(long, default_value_t = 0)]
+    seed: u64,
+
+    /// Key files to spread over the consumers by their final partitions
+    #[clap(long = "keys")]
+    key_files: Vec<PathBuf>,
+
+    /// Partitioning scheme that places the keys
+    #[clap(long, value_enum, default_value_t)]
+    scheme: Scheme,
+
+    #[command(flatten)]
+    fields: RecordFields,
+
+    /// Print JSON instead of a table
+    #[clap(long)]
+    json: bool,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Strategy {
+    #[default]
+    Balanced,
+    Greedy,
+}
+
+/// One record of the ownership store.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Ownership {
+    pub partition_id: u16,
+    /// Empty once the owner released the partition
+    pub owner_id: String,
+    /// Seconds into the simulation of the last claim or renewal
+    pub last_modified: u64,
+    /// Incremented on every write, the store's ETag
+    pub version: u64,
+}
+
+/// Ownership records in memory, mirrored to a JSON file when there is one.
+#[derive(Debug, Default)]
+pub struct Store {
+    records: BTreeMap<u16, Ownership>,
+    path: Option<PathBuf>,
+}
+
+impl Store {
+    /// An empty store; an existing file is overwritten.
+    pub fn create(path: Option<&Path>) -> Result<Self, String> {
+        let store = Store {
+            records: BTreeMap::new(),
+            path: path.map(Path::to_path_buf),
+        };
+        store.save()?;
+        Ok(store)
+    }
+
+    pub fn load(path: &Path) -> Result<Vec<Ownership>, String> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| format!("cannot read {}: {e}", path.display()))?;
+        serde_json::from_str(&text).map_err(|e| format!("{}: {e}", path.display()))
+    }
+
+    fn save(&self) -> Result<(), String> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let records: Vec<&Ownership> = self.records.values().collect();
+        let json = serde_json::to_string_pretty(&records).map_err(|e| e.to_string())?;
+        std::fs::write(path, json).map_err(|e| format!("cannot write {}: {e}", path.display()))
+    }
+
+    pub fn list(&mut self) -> Result<Vec<Ownership>, String> {
+        if let Some(path) = &self.path {
+            self.records = Store::load(path)?
+                .into_iter()
+                .map(|record| (record.partition_id, record))
+                .collect();
+        }
+        Ok(self.records.values().cloned().collect())
+    }
+
+    /// Writes the claims whose version still matches the stored record (0 for a partition
+    /// that was never claimed) and returns the ones that succeeded.
+    pub fn claim(&mut self, claims: Vec<Ownership>) -> Result<Vec<Ownership>, String> {
+        let mut claimed = Vec::new();
+        for mut claim in claims {
+            let current = self
+                .records
+                .get(&claim.partition_id)
+                .map_or(0, |record| record.version);
+            if current == claim.version {
+                claim.version += 1;
+                self.records.insert(claim.partition_id, claim.clone());
+                claimed.push(claim);
+            }
+        }
+        self.save()?;
+        Ok(claimed)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Action {
+    Join,
+    Leave,
+    Crash,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Event {
+    pub time: u64,
+    pub action: Action,
+    pub consumer: String,
+}
+
+pub fn parse_events(text: &str) -> Result<Vec<Event>, String> {
+    let mut events = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        if line.is_empty() {
+            continue;
+        }
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let [time, action, consumer] = fields[..] else {
+            return Err(format!(
+                "line {}: expected `<seconds> join|leave|crash <consumer>`, got {line:?}",
+                number + 1
+            ));
+        };
+        let time = time
+            .parse()
+            .map_err(|_| format!("line {}: {time:?} is not a number of seconds", number + 1))?;
+        let action = match action {
+            "join" => Action::Join,
+            "leave" => Action::Leave,
+            "crash" => Action::Crash,
+            other => return Err(format!("line {}: unknown action {other:?}", number + 1)),
+        };
+        events.push(Event {
+            time,
+            action,
+            consumer: consumer.to_string(),
+        });
+    }
+    events.sort_by_key(|event| event.time);
+    Ok(events)
+}
+
+/// A partition that changed hands; `None` is nobody (never claimed, released or expired).
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Handover {
+    pub time: u64,
+    pub partition_id: u16,
+    pub from: Option<String>,
+    pub to: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Simulation {
+    pub handovers: Vec<Handover>,
+    /// Owner of every partition at the end, `None` for unowned partitions
+    pub owners: Vec<Option<String>>,
+    /// Seconds from the last event until every partition was owned and nothing moved again
+    pub settled_after: Option<u64>,
+}
+
+pub struct Settings {
+    pub partition_count: i16,
+    pub strategy: Strategy,
+    pub interval: u64,
+    pub expiration: u64,
+    pub until: u64,
+    pub seed: u64,
+}
+
+/// splitmix64, to make the random choices reproducible.
+struct Random(u64);
+
+impl Random {
+    fn below(&mut self, bound: usize) -> usize {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        ((z ^ (z >> 31)) % bound as u64) as usize
+    }
+
+    fn pick<T: Copy>(&mut self, items: &[T]) -> T {
+        items[self.below(items.len())]
+    }
+}
+
+fn active_owners(records: &[Ownership], now: u64, expiration: u64) -> Vec<Option<String>> {
+    let mut owners = Vec::new();
+    for record in records {
+        let index = record.partition_id as usize;
+        if owners.len() <= index {
+            owners.resize(index + 1, None);
+        }
+        if !record.owner_id.is_empty() && now < record.last_modified.saturating_add(expiration) {
+            owners[index] = Some(record.owner_id.clone());
+        }
+    }
+    owners
+}
+
+/// One load-balancing cycle of `me`: renew, then claim while below the share.
+fn balance(
+    me: &str,
+    store: &mut Store,
+    settings: &Settings,
+    now: u64,
+    random: &mut Random,
+) -> Result<(), String> {
+    let records = store.list()?;
+    let renewals = records
+        .iter()
+        .filter(|record| {
+            record.owner_id == me && now < record.last_modified.saturating_add(settings.expiration)
+        })
+        .map(|record| Ownership {
+            last_modified: now,
+            ..record.clone()
+        })
+        .collect();
+    store.claim(renewals)?;
+
+    loop {
+        let records = store.list()?;
+        let mut owners = active_owners(&records, now, settings.expiration);
+        owners.resize(settings.partition_count as usize, None);
+        let mut owned: BTreeMap<&str, Vec<u16>> = BTreeMap::new();
+        owned.insert(me, Vec::new());
+        for (partition_id, owner) in owners.iter().enumerate() {
+            if let Some(owner) = owner {
+                owned.entry(owner).or_default().push(partition_id as u16);
+            }
+        }
+
+        let partitions = settings.partition_count as usize;
+        let minimum = partitions / owned.len();
+        let maximum = minimum + 1;
+        let at_maximum: Vec<&str> = owned
+            .iter()
+            .filter(|(_, partitions)| partitions.len() == maximum)
+            .map(|(owner, _)| *owner)
+            .collect();
+        let above_maximum: Vec<&str> = owned
+            .iter()
+            .filter(|(_, partitions)| partitions.len() > maximum)
+            .map(|(owner, _)| *owner)
+            .collect();
+        let mine = owned[me].len();
+        let may_claim =
+            mine < minimum || (mine == minimum && at_maximum.len() < partitions % owned.len());
+        if !may_claim {
+            return Ok(());
+        }
+
+        let unowned: Vec<u16> = (0..partitions as u16)
+            .filter(|partition_id| owners[*partition_id as usize].is_none())
+            .collect();
+        let target = if !unowned.is_empty() {
+            random.pick(&unowned)
+        } else if !above_maximum.is_empty() {
+            let victim = random.pick(&above_maximum);
+            random.pick(&owned[victim])
+        } else if mine < minimum && !at_maximum.is_empty() {
+            let victim = random.pick(&at_maximum);
+            random.pick(&owned[victim])
+        } else {
+            return Ok(());
+        };
+        let version = records
+            .iter()
+            .find(|record| record.partition_id == target)
+            .map_or(0, |record| record.version);
+        let claimed = store.claim(vec![Ownership {
+            partition_id: target,
+            owner_id: me.to_string(),
+            last_modified: now,
+            version,
+        }])?;
+        if claimed.is_empty() || settings.strategy == Strategy::Balanced {
+            return Ok(());
+        }
+    }
+}
+
+pub fn simulate(
+    events: &[Event],
+    settings: &Settings,
+    store: &mut Store,
+) -> Result<Simulation, String> {
+    let mut random = Random(settings.seed);
+    // Running consumers and the time of their next cycle.
+    let mut consumers: BTreeMap<String, u64> = BTreeMap::new();
+    let mut owners: Vec<Option<String>> = vec![None; settings.partition_count as usize];
+    let mut handovers = Vec::new();
+    let mut pending = events.iter().peekable();
+
+    for now in 0..=settings.until {
+        while let Some(event) = pending.next_if(|event| event.time <= now) {
+            match event.action {
+                Action::Join => {
+                    consumers.entry(event.consumer.clone()).or_insert(now);
+                }
+                Action::Crash => {
+                    consumers.remove(&event.consumer);
+                }
+                Action::Leave => {
+                    if consumers.remove(&event.consumer).is_some() {
+                        let releases = store
+                            .list()?
+                            .into_iter()
+                            .filter(|record| record.owner_id == event.consumer)
+                            .map(|record| Ownership {
+                                owner_id: String::new(),
+                                last_modified: now,
+                                ..record
+                            })
+                            .collect();
+                        store.claim(releases)?;
+                    }
+                }
+            }
+        }
+
+        let due: Vec<String> = consumers
+            .iter()
+            .filter(|(_, next)| **next <= now)
+            .map(|(consumer, _)| consumer.clone())
+            .collect();
+        for consumer in due {
+            balance(&consumer, store, settings, now, &mut random)?;
+            consumers.insert(consumer, now.saturating_add(settings.interval));
+        }
+
+        let mut current = active_owners(&store.list()?, now, settings.expiration);
+        current.resize(settings.partition_count as usize, None);
+        for (partition_id, (before, after)) in owners.iter().zip(&current).enumerate() {
+            if before != after {
+                handovers.push(Handover {
+                    time: now,
+                    partition_id: partition_id as u16,
+                    from: before.clone(),
+                    to: after.clone(),
+                });
+            }
+        }
+        owners = current;
+    }
+
+    let last_event = events.last().map_or(0, |event| event.time);
+    let settled_after = (owners.iter().all(Option::is_some) && !consumers.is_empty()).then(|| {
+        handovers
+            .last()
+            .map_or(0, |handover| handover.time.saturating_sub(last_event))
+    });
+    Ok(Simulation {
+        handovers,
+        owners,
+        settled_after,
+    })
+}
+
+#[derive(Debug, Default, Serialize, PartialEq)]
+pub struct ConsumerLoad {
+    pub consumer: String,
+    pub partitions: Vec<u16>,
+    pub keys: u64,
+    pub weight: f64,
+}
+
+/// Partitions, keys and weight every consumer ends up with.
+pub fn consumer_loads(
+    owners: &[Option<String>],
+    weights: &HashMap<String, f64>,
+    scheme: Scheme,
+) -> Vec<ConsumerLoad> {
+    let mut loads: BTreeMap<&str, ConsumerLoad> = BTreeMap::new();
+    for (partition_id, owner) in owners.iter().enumerate() {
+        let owner = owner.as_deref().unwrap_or("-");
+        let load = loads.entry(owner).or_insert_with(|| ConsumerLoad {
+            consumer: owner.to_string(),
+            ..ConsumerLoad::default()
+        });
+        load.partitions.push(partition_id as u16);
+    }
+    let partitioner = scheme.partitioner(owners.len() as i16);
+    for (key, weight) in weights {
+        let owner = owners[partitioner.partition_of(key) as usize]
+            .as_deref()
+            .unwrap_or("-");
+        let load = loads.get_mut(owner).unwrap();
+        load.keys += 1;
+        load.weight += weight;
+    }
+    loads.into_values().collect()
+}
+
+/// `--until`, or one minute after the last event.
+fn simulated_until(until: Option<u64>, events: &[Event]) -> Result<u64, String> {
+    let until = until.unwrap_or_else(|| {
+        events
+            .last()
+            .map_or(0, |event| event.time.saturating_add(60))
+    });
+    if until > MAX_UNTIL {
+        return Err(format!(
+            "cannot simulate {until} seconds, at most {MAX_UNTIL} (a week); pass a smaller --until"
+        ));
+    }
+    Ok(until)
+}
+
+pub fn run(args: &ProcessorArgs) -> Result<(), String> {
+    args.scheme.check_partition_count(args.partition_count)?;
+    if args.interval == 0 || args.expiration == 0 {
+        return Err("--interval and --expiration must be at least one second".to_string());
+    }
+    let text = std::fs::read_to_string(&args.events)
+        .map_err(|e| format!("cannot read {}: {e}", args.events.display()))?;
+    let events = parse_events(&text).map_err(|e| format!("{}: {e}", args.events.display()))?;
+    let settings = Settings {
+        partition_count: args.partition_count,
+        strategy: args.strategy,
+        interval: args.interval,
+        expiration: args.expiration,
+        until: simulated_until(args.until, &events)?,
+        seed: args.seed,
+    };
+    let mut store = Store::create(args.store.as_deref())?;
+    let simulation = simulate(&events, &settings, &mut store)?;
+    let (weights, skipped_lines) = if args.key_files.is_empty() {
+        (HashMap::new(), 0)
+    } else {
+        read_key_files(&args.key_files, &args.fields)?
+    };
+    let loads = consumer_loads(&simulation.owners, &weights, args.scheme);
+
+    if args.json {
+        #[derive(Serialize)]
+        struct Output<'a> {
+            #[serde(flatten)]
+            simulation: &'a Simulation,
+            consumers: &'a [ConsumerLoad],
+            skipped_lines: u64,
+        }
+        return print_json(&Output {
+            simulation: &simulation,
+            consumers: &loads,
+            skipped_lines,
+        });
+    }
+
+    let name = |owner: &Option<String>| owner.clone().unwrap_or_else(|| "-".to_string());
+    println!("{:>6} {:>9}  owner", "time", "partition");
+    for handover in &simulation.handovers {
+        println!(
+            "{:>6} {:>9}  {} → {}",
+            handover.time,
+            handover.partition_id,
+            name(&handover.from),
+            name(&handover.to)
+        );
+    }
+    println!();
+    match simulation.settled_after {
+        Some(seconds) => println!("balanced {seconds}s after the last event"),
+        None => println!("not every partition is owned at {}s", settings.until),
+    }
+    println!();
+    println!(
+        "{:>16} {:>10} {:>8} {:>10}  partitions",
+        "consumer", "keys", "weight", "share"
+    );
+    let total: f64 = loads.iter().map(|load| load.weight).sum();
+    for load in &loads {
+        let partitions: Vec<String> = load.partitions.iter().map(u16::to_string).collect();
+        println!(
+            "{:>16} {:>10} {:>8} {:>9.1}%  {}",
+            load.consumer,
+            load.keys,
+            format_weight(load.weight),
+            if total == 0.0 {
+                0.0
+            } else {
+                100.0 * load.weight / total
+            },
+            partitions.join(",")
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(strategy: Strategy, until: u64) -> Settings {
+        Settings {
+            partition_count: 8,
+            strategy,
+            interval: 10,
+            expiration: 30,
+            until,
+            seed: 7,
+        }
+    }
+
+    fn owned_counts(simulation: &Simulation) -> BTreeMap<String, usize> {
+        let mut counts = BTreeMap::new();
+        for owner in simulation.owners.iter().flatten() {
+            *counts.entry(owner.clone()).or_default() += 1;
+        }
+        counts
+    }
+
+    #[test]
+    fn test_consumers_balance_and_take_over() -> Result<(), String> {
+        let events = parse_events("0 join a\n0 join b\n5 join c # late\n200 crash c\n")?;
+
+        let mut store = Store::default();
+        let simulation = simulate(&events, &settings(Strategy::Balanced, 199), &mut store)?;
+        let mut counts: Vec<usize> = owned_counts(&simulation).into_values().collect();
+        counts.sort();
+        assert_eq!(counts, vec![2, 3, 3]);
+
+        // The crashed consumer's partitions expire and the others split them.
+        let mut store = Store::default();
+        let simulation = simulate(&events, &settings(Strategy::Greedy, 300), &mut store)?;
+        let counts = owned_counts(&simulation);
+        assert_eq!(counts.values().copied().collect::<Vec<_>>(), vec![4, 4]);
+        assert!(simulation
+            .handovers
+            .iter()
+            .any(|handover| handover.time >= 230 && handover.from.is_none()));
+
+        let weights: HashMap<String, f64> = (0..100).map(|i| (format!("k{i}"), 1.0)).collect();
+        let loads = consumer_loads(&simulation.owners, &weights, Scheme::ServiceBus);
+        assert_eq!(loads.iter().map(|load| load.keys).sum::<u64>(), 100);
+        Ok(())
+    }
+
+    #[test]
+    fn test_extreme_times_do_not_wrap() -> Result<(), String> {
+        let record = |last_modified| Ownership {
+            partition_id: 0,
+            owner_id: "a".to_string(),
+            last_modified,
+            version: 1,
+        };
+        let owners = active_owners(&[record(5)], 1000, u64::MAX);
+        assert_eq!(owners, [Some("a".to_string())]);
+        assert_eq!(active_owners(&[record(5)], 1000, 30), [None]);
+
+        let late = parse_events("18446744073709551600 join a\n")?;
+        assert!(simulated_until(None, &late).is_err());
+        assert_eq!(simulated_until(Some(100), &late)?, 100);
+        assert_eq!(simulated_until(None, &parse_events("5 join a")?)?, 65);
+        Ok(())
+    }
+
+    #[test]
+    fn test_store_rejects_stale_versions() -> Result<(), String> {
+        let path = std::env::temp_dir().join(format!("ownership-{}.json", std::process::id()));
+        let mut store = Store::create(Some(&path))?;
+        let claim = |owner: &str, version| Ownership {
+            partition_id: 3,
+            owner_id: owner.to_string(),
+            last_modified: 0,
+            version,
+        };
+        assert_eq!(store.claim(vec![claim("a", 0)])?.len(), 1);
+        assert!(store.claim(vec![claim("b", 0)])?.is_empty());
+        assert_eq!(Store::load(&path)?, vec![claim("a", 1)]);
+        std::fs::remove_file(&path).map_err(|e| e.to_string())?;
+        Ok(())
+    }
+}
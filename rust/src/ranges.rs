@@ -0,0 +1,474 @@
+use clap::Args;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+
+use crate::{get_ranges, print_json, to_partition_id};
+
+/// Number of logical partitions the key hashes are reduced to.
+pub const LOGICAL_PARTITION_COUNT: i32 = i16::MAX as i32;
+
+/// User-defined range tables from `--range-table`, by partition count.
+static CUSTOM_TABLES: OnceLock<HashMap<i16, CustomTable>> = OnceLock::new();
+
+#[derive(Debug)]
+struct CustomTable {
+    path: PathBuf,
+    table: Vec<i32>,
+    /// Whether any mode asked for the table's partition count
+    used: AtomicBool,
+}
+
+#[derive(Args, Debug)]
+pub struct RangesArgs {
+    /// Number of partitions
+    #[clap(short = 'c', long, value_parser = clap::value_parser!(i16).range(1..))]
+    partition_count: i16,
+
+    /// Only print the partition that owns this logical partition
+    #[clap(short = 'l', long, conflicts_with = "compare",
+        value_parser = clap::value_parser!(i32).range(0..LOGICAL_PARTITION_COUNT as i64))]
+    logical: Option<i32>,
+
+    /// Second partition count to compare the ranges against
+    #[clap(long, value_parser = clap::value_parser!(i16).range(1..))]
+    compare: Option<i16>,
+
+    /// Print JSON instead of a table
+    #[clap(long)]
+    json: bool,
+
+    /// Print the standard table as inclusive upper bounds, the format `--range-table` reads
+    #[clap(long, conflicts_with_all = ["logical", "compare", "json"])]
+    export: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Range {
+    pub partition_id: u16,
+    pub first_logical: i32,
+    pub last_logical: i32,
+    pub width: i32,
+}
+
+/// A stretch of logical partitions that belongs to the same partition under both counts.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Overlap {
+    pub first_logical: i32,
+    pub last_logical: i32,
+    pub width: i32,
+    pub from_partition_id: u16,
+    pub to_partition_id: u16,
+}
+
+/// "Partition `partition_id` of `partition_count`".
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Constraint {
+    pub partition_count: i16,
+    pub partition_id: u16,
+}
+
+impl Constraint {
+    pub fn new(partition_count: i16, partition_id: u16) -> Result<Self, String> {
+        if partition_count < 1 || partition_id as i32 >= partition_count as i32 {
+            return Err(format!(
+                "partition {partition_id} does not exist with {partition_count} partitions"
+            ));
+        }
+        Ok(Constraint {
+            partition_count,
+            partition_id,
+        })
+    }
+
+    pub fn range(&self) -> Range {
+        ranges(self.partition_count)[self.partition_id as usize].clone()
+    }
+}
+
+impl FromStr for Constraint {
+    type Err = String;
+
+    /// Parses `COUNT:ID`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (count, id) = s
+            .split_once(':')
+            .ok_or_else(|| format!("expected COUNT:ID, got {s:?}"))?;
+        let count = count
+            .trim()
+            .parse()
+            .map_err(|_| format!("invalid partition count in {s:?}"))?;
+        let id = id
+            .trim()
+            .parse()
+            .map_err(|_| format!("invalid partition id in {s:?}"))?;
+        Constraint::new(count, id)
+    }
+}
+
+/// Parses a range table: the inclusive upper logical partition of every partition, one per
+/// line and ascending, the last one being 32766. `#` starts a comment.
+pub fn parse_range_table(text: &str) -> Result<Vec<i32>, String> {
+    let mut table: Vec<i32> = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        if line.is_empty() {
+            continue;
+        }
+        let bound: i32 = line
+            .parse()
+            .ok()
+            .filter(|bound| (0..LOGICAL_PARTITION_COUNT).contains(bound))
+            .ok_or_else(|| format!("line {}: {line:?} is not a logical partition", number + 1))?;
+        if let Some(previous) = table.last().filter(|previous| bound <= **previous) {
+            return Err(format!(
+                "line {}: {bound} does not come after the previous bound {previous}",
+                number + 1
+            ));
+        }
+        table.push(bound);
+    }
+    match table.last() {
+        None => Err("the range table is empty".to_string()),
+        Some(last) if *last != LOGICAL_PARTITION_COUNT - 1 => Err(format!(
+            "the last range ends at {last} instead of {}, so the table does not cover every \
+             logical partition",
+            LOGICAL_PARTITION_COUNT - 1
+        )),
+        Some(_) => Ok(table),
+    }
+}
+
+pub fn load_range_table(path: &Path) -> Result<Vec<i32>, String> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| format!("cannot read {}: {e}", path.display()))?;
+    parse_range_table(&text).map_err(|e| format!("{}: {e}", path.display()))
+}
+
+/// Makes every mode use the tables in `paths` instead of the even split for their partition
+/// counts. Call once, before anything maps keys to partitions.
+pub fn install_range_tables(paths: &[PathBuf]) -> Result<(), String> {
+    let mut tables = HashMap::new();
+    for path in paths {
+        let table = load_range_table(path)?;
+        let count = table.len() as i16;
+        let custom = CustomTable {
+            path: path.clone(),
+            table,
+            used: AtomicBool::new(false),
+        };
+        if tables.insert(count, custom).is_some() {
+            return Err(format!(
+                "{}: there is already a range table for {count} partitions",
+                path.display()
+            ));
+        }
+    }
+    CUSTOM_TABLES
+        .set(tables)
+        .map_err(|_| "the range tables are already installed".to_string())
+}
+
+/// The range table for a partition count: a user-defined table from `--range-table` when
+/// there is one for this count, the even split of `get_ranges` otherwise.
+pub fn range_table(partition_count: i16) -> Vec<i32> {
+    match CUSTOM_TABLES
+        .get()
+        .and_then(|tables| tables.get(&partition_count))
+    {
+        Some(custom) => {
+            custom.used.store(true, Ordering::Relaxed);
+            custom.table.clone()
+        }
+        None => get_ranges(partition_count),
+    }
+}
+
+/// Fails for installed tables whose partition count no mode asked for, since they would have
+/// been ignored silently, e.g. a 33-line table passed with `-c 32`.
+pub fn check_range_tables_used() -> Result<(), String> {
+    CUSTOM_TABLES.get().map_or(Ok(()), |tables| {
+        unused_tables(tables, |_, custom| custom.used.load(Ordering::Relaxed))
+    })
+}
+
+/// The same check ahead of the run, for modes that know every partition count they will ask
+/// for, so they fail before printing anything.
+pub fn check_range_tables_cover(partition_counts: &[i16]) -> Result<(), String> {
+    CUSTOM_TABLES.get().map_or(Ok(()), |tables| {
+        unused_tables(tables, |count, _| partition_counts.contains(&count))
+    })
+}
+
+fn unused_tables(
+    tables: &HashMap<i16, CustomTable>,
+    used: impl Fn(i16, &CustomTable) -> bool,
+) -> Result<(), String> {
+    let mut unused: Vec<String> = tables
+        .iter()
+        .filter(|(count, custom)| !used(**count, custom))
+        .map(|(count, custom)| format!("{} ({count} partitions)", custom.path.display()))
+        .collect();
+    if unused.is_empty() {
+        return Ok(());
+    }
+    unused.sort();
+    Err(format!(
+        "range table {} was not used: a table only replaces the even split for the partition \
+         count given by its number of lines",
+        unused.join(", ")
+    ))
+}
+
+impl RangesArgs {
+    /// The partition counts whose range tables the run looks up.
+    pub fn partition_counts(&self) -> Vec<i16> {
+        if self.export {
+            return Vec::new();
+        }
+        [Some(self.partition_count), self.compare]
+            .into_iter()
+            .flatten()
+            .collect()
+    }
+}
+
+pub fn run(args: &RangesArgs) -> Result<(), String> {
+    if args.export {
+        for bound in get_ranges(args.partition_count) {
+            println!("{bound}");
+        }
+        return Ok(());
+    }
+    let table = ranges(args.partition_count);
+
+    if let Some(logical) = args.logical {
+        let range = &table[to_partition_id(&range_table(args.partition_count), logical) as usize];
+        if args.json {
+            #[derive(Serialize)]
+            struct Lookup<'a> {
+                logical_partition: i32,
+                #[serde(flatten)]
+                range: &'a Range,
+            }
+            print_json(&Lookup {
+                logical_partition: logical,
+                range,
+            })?;
+        } else {
+            println!("{}", range.partition_id);
+        }
+        return Ok(());
+    }
+
+    if let Some(other_count) = args.compare {
+        let overlaps = compare(args.partition_count, other_count);
+        if args.json {
+            print_json(&overlaps)?;
+        } else {
+            println!(
+                "{:>13} {:>12} {:>6} {:>9} {:>9}",
+                "first_logical",
+                "last_logical",
+                "width",
+                format!("id_of_{}", args.partition_count),
+                format!("id_of_{other_count}")
+            );
+            for overlap in &overlaps {
+                println!(
+                    "{:>13} {:>12} {:>6} {:>9} {:>9}",
+                    overlap.first_logical,
+                    overlap.last_logical,
+                    overlap.width,
+                    overlap.from_partition_id,
+                    overlap.to_partition_id
+                );
+            }
+        }
+        return Ok(());
+    }
+
+    if args.json {
+        print_json(&table)?;
+    } else {
+        println!(
+            "{:>12} {:>13} {:>12} {:>6}",
+            "partition_id", "first_logical", "last_logical", "width"
+        );
+        for range in &table {
+            println!(
+                "{:>12} {:>13} {:>12} {:>6}",
+                range.partition_id, range.first_logical, range.last_logical, range.width
+            );
+        }
+    }
+    Ok(())
+}
+
+/// The logical-partition interval of every partition, as laid out by [`range_table`].
+pub fn ranges(partition_count: i16) -> Vec<Range> {
+    let mut first_logical = 0;
+    range_table(partition_count)
+        .into_iter()
+        .enumerate()
+        .map(|(partition_id, last_logical)| {
+            let range = Range {
+                partition_id: partition_id as u16,
+                first_logical,
+                last_logical,
+                width: last_logical - first_logical + 1,
+            };
+            first_logical = last_logical + 1;
+            range
+        })
+        .collect()
+}
+
+/// Splits the logical partition space wherever either count starts a new partition.
+pub fn compare(from_count: i16, to_count: i16) -> Vec<Overlap> {
+    let from = ranges(from_count);
+    let to = ranges(to_count);
+    let (mut i, mut j) = (0, 0);
+    let mut overlaps = Vec::with_capacity(from.len() + to.len());
+    while i < from.len() && j < to.len() {
+        let first_logical = i32::max(from[i].first_logical, to[j].first_logical);
+        let last_logical = i32::min(from[i].last_logical, to[j].last_logical);
+        overlaps.push(Overlap {
+            first_logical,
+            last_logical,
+            width: last_logical - first_logical + 1,
+            from_partition_id: from[i].partition_id,
+            to_partition_id: to[j].partition_id,
+        });
+        if from[i].last_logical == last_logical {
+            i += 1;
+        }
+        if to[j].last_logical == last_logical {
+            j += 1;
+        }
+    }
+    overlaps
+}
+
+/// The logical partitions that satisfy every constraint, or an explanation why there are none.
+pub fn intersect(constraints: &[Constraint]) -> Result<RangeInclusive<i32>, String> {
+    let mut first_logical = 0;
+    let mut last_logical = LOGICAL_PARTITION_COUNT - 1;
+    for constraint in constraints {
+        let range = constraint.range();
+        first_logical = i32::max(first_logical, range.first_logical);
+        last_logical = i32::min(last_logical, range.last_logical);
+    }
+    if first_logical > last_logical {
+        let intervals: Vec<String> = constraints
+            .iter()
+            .map(|constraint| {
+                let range = constraint.range();
+                format!(
+                    "partition {} of {} = [{}, {}]",
+                    constraint.partition_id,
+                    constraint.partition_count,
+                    range.first_logical,
+                    range.last_logical
+                )
+            })
+            .collect();
+        return Err(format!(
+            "the constraints can never be met together, their logical intervals do not overlap: {}",
+            intervals.join(", ")
+        ));
+    }
+    Ok(first_logical..=last_logical)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ranges_cover_logical_space() {
+        for count in [1, 2, 3, 32, 1000, i16::MAX] {
+            let table = ranges(count);
+            assert_eq!(table.len(), count as usize);
+            assert_eq!(table[0].first_logical, 0);
+            assert_eq!(
+                table.last().unwrap().last_logical,
+                LOGICAL_PARTITION_COUNT - 1
+            );
+            assert_eq!(
+                table.iter().map(|range| range.width).sum::<i32>(),
+                LOGICAL_PARTITION_COUNT
+            );
+        }
+        assert_eq!(ranges(32)[0].width, 1024);
+        assert_eq!(ranges(32)[31].width, 1023);
+    }
+
+    #[test]
+    fn test_compare_refines_both_tables() {
+        let overlaps = compare(2, 3);
+        let ids: Vec<(u16, u16)> = overlaps
+            .iter()
+            .map(|overlap| (overlap.from_partition_id, overlap.to_partition_id))
+            .collect();
+        assert_eq!(ids, vec![(0, 0), (0, 1), (1, 1), (1, 2)]);
+        assert_eq!(
+            overlaps.iter().map(|overlap| overlap.width).sum::<i32>(),
+            LOGICAL_PARTITION_COUNT
+        );
+    }
+
+    #[test]
+    fn test_intersect_constraints() -> Result<(), String> {
+        let parse = |specs: &[&str]| -> Result<Vec<Constraint>, String> {
+            specs.iter().map(|spec| spec.parse()).collect()
+        };
+        // 3 of 4 is [24576, 32766], 13 of 32 is [13312, 14335].
+        assert!(intersect(&parse(&["4:3", "32:13"])?).is_err());
+        assert_eq!(
+            intersect(&parse(&["4:1", "16:6", "32:13"])?)?,
+            13312..=14335
+        );
+        assert_eq!(intersect(&[])?, 0..=LOGICAL_PARTITION_COUNT - 1);
+        assert!("4:4".parse::<Constraint>().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_range_table_files() -> Result<(), String> {
+        let exported: String = get_ranges(32)
+            .iter()
+            .map(|bound| format!("{bound}\n"))
+            .collect();
+        assert_eq!(parse_range_table(&exported)?, get_ranges(32));
+
+        // Partition 1 of 4 split in two, as after a hypothetical split.
+        let split = parse_range_table("8191\n12287 # split\n16383\n24575\n32766\n")?;
+        assert_eq!(split.len(), 5);
+        assert_eq!(to_partition_id(&split, 12288), 2);
+        assert!(parse_range_table("100\n100\n32766").is_err());
+        assert!(parse_range_table("100\n32765").is_err());
+        assert!(parse_range_table("32767").is_err());
+        assert!(parse_range_table("# empty").is_err());
+
+        let tables = HashMap::from([(
+            5,
+            CustomTable {
+                path: "split.txt".into(),
+                table: split,
+                used: AtomicBool::new(false),
+            },
+        )]);
+        let flagged = |_, custom: &CustomTable| custom.used.load(Ordering::Relaxed);
+        assert!(unused_tables(&tables, flagged).is_err());
+        tables[&5].used.store(true, Ordering::Relaxed);
+        assert!(unused_tables(&tables, flagged).is_ok());
+        assert!(unused_tables(&tables, |count, _| [4, 6].contains(&count)).is_err());
+        assert!(unused_tables(&tables, |count, _| [5].contains(&count)).is_ok());
+        Ok(())
+    }
+}
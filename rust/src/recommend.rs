@@ -0,0 +1,329 @@
+use clap::{Args, ValueEnum};
+use rayon::prelude::*;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use crate::partitioner::Scheme;
+use crate::print_json;
+use crate::ranges::range_table;
+use crate::records::{read_key_files, RecordFields};
+use crate::{to_logical_encoded, to_partition_id, KeyEncoding};
+
+#[derive(Args, Debug)]
+pub struct RecommendArgs {
+    /// Sample key files with one record per line (reads stdin when omitted)
+    files: Vec<PathBuf>,
+
+    /// Smallest partition count to evaluate
+    #[clap(long, default_value_t = 1, value_parser = clap::value_parser!(i16).range(1..))]
+    from: i16,
+
+    /// Largest partition count to evaluate
+    #[clap(long, default_value_t = 32, value_parser = clap::value_parser!(i16).range(1..))]
+    to: i16,
+
+    /// Partitioning scheme that places the keys
+    #[clap(long, value_enum, default_value_t)]
+    scheme: Scheme,
+
+    /// Partition count in use today, to count the keys every candidate would move
+    #[clap(long, value_parser = clap::value_parser!(i16).range(1..))]
+    current: Option<i16>,
+
+    /// Load a single partition can take, in the unit of the weights (messages when unweighted)
+    #[clap(long)]
+    limit: Option<f64>,
+
+    #[command(flatten)]
+    fields: RecordFields,
+
+    /// What to rank the candidates by; ties go to the smaller partition count
+    #[clap(long, value_enum, default_value_t)]
+    rank_by: RankBy,
+
+    /// Only list the best candidates
+    #[clap(short = 'n', long)]
+    top: Option<usize>,
+
+    /// Print JSON instead of a Markdown table
+    #[clap(long)]
+    json: bool,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum RankBy {
+    /// The busiest partition compared with the mean, candidates within the limit first
+    Skew,
+    /// The load of the busiest partition, candidates within the limit first
+    #[default]
+    Load,
+    /// The share of the load that moves from the current partition count
+    Moved,
+}
+
+/// The sample with the weights of every distinct key summed up.
+#[derive(Debug, Default)]
+pub struct Sample {
+    /// Every distinct key with its weight
+    keys: Vec<(String, f64)>,
+    /// Logical partition of every key, so the Service Bus scheme places the sample for any
+    /// partition count with range lookups alone
+    logicals: Vec<i32>,
+    pub skipped_lines: u64,
+}
+
+/// The partition of every key of a sample under one scheme and partition count.
+#[derive(Debug)]
+pub struct Placement {
+    pub scheme: Scheme,
+    partitions: Vec<u16>,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+pub struct Candidate {
+    pub partition_count: i16,
+    /// Load of the busiest partition divided by the mean load
+    pub max_to_mean: f64,
+    /// Standard deviation of the partition loads divided by their mean
+    pub variation: f64,
+    pub max_load: f64,
+    /// `max_load` relative to `--limit`, 1.0 being the limit
+    pub utilization: Option<f64>,
+    pub empty_partitions: u16,
+    pub keys_moved: Option<u64>,
+    pub load_moved: Option<f64>,
+}
+
+impl Sample {
+    pub fn new(weights: HashMap<String, f64>, skipped_lines: u64) -> Self {
+        let keys: Vec<(String, f64)> = weights.into_iter().collect();
+        Sample {
+            logicals: keys
+                .iter()
+                .map(|(key, _)| to_logical_encoded(key, KeyEncoding::Utf8))
+                .collect(),
+            keys,
+            skipped_lines,
+        }
+    }
+
+    /// Places every key; only schemes other than Service Bus hash the keys again.
+    pub fn place(&self, scheme: Scheme, partition_count: i16) -> Placement {
+        let partitions = match scheme {
+            Scheme::ServiceBus => {
+                let ranges = range_table(partition_count);
+                self.logicals
+                    .iter()
+                    .map(|logical| to_partition_id(&ranges, *logical))
+                    .collect()
+            }
+            _ => {
+                let partitioner = scheme.partitioner(partition_count);
+                self.keys
+                    .iter()
+                    .map(|(key, _)| partitioner.partition_of(key))
+                    .collect()
+            }
+        };
+        Placement { scheme, partitions }
+    }
+
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    pub fn evaluate(
+        &self,
+        scheme: Scheme,
+        partition_count: i16,
+        current: Option<&Placement>,
+        limit: Option<f64>,
+    ) -> Candidate {
+        debug_assert!(current.is_none_or(|current| current.scheme == scheme));
+        let placement = self.place(scheme, partition_count);
+        let mut loads = vec![0.0; partition_count as usize];
+        let (mut keys_moved, mut load_moved) = (0, 0.0);
+        for (index, (_, weight)) in self.keys.iter().enumerate() {
+            let partition_id = placement.partitions[index];
+            loads[partition_id as usize] += weight;
+            // A key stays where it is only if its partition id is the same under both counts.
+            if let Some(current) = current {
+                if current.partitions[index] != partition_id {
+                    keys_moved += 1;
+                    load_moved += weight;
+                }
+            }
+        }
+
+        let total: f64 = loads.iter().sum();
+        let mean = total / loads.len() as f64;
+        let max_load = loads.iter().copied().fold(0.0, f64::max);
+        let variance =
+            loads.iter().map(|load| (load - mean).powi(2)).sum::<f64>() / loads.len() as f64;
+        let relative = |value: f64| if mean > 0.0 { value / mean } else { 0.0 };
+        Candidate {
+            partition_count,
+            max_to_mean: relative(max_load),
+            variation: relative(variance.sqrt()),
+            max_load,
+            utilization: limit.map(|limit| max_load / limit),
+            empty_partitions: loads.iter().filter(|load| **load == 0.0).count() as u16,
+            keys_moved: current.map(|_| keys_moved),
+            load_moved: current.map(|_| if total > 0.0 { load_moved / total } else { 0.0 }),
+        }
+    }
+}
+
+pub fn rank(candidates: &mut [Candidate], rank_by: RankBy) {
+    // A single partition is never skewed, so skew alone would always pick it.
+    let over = |candidate: &Candidate| candidate.utilization.is_some_and(|use_| use_ > 1.0);
+    candidates.sort_by(|a, b| {
+        let order = match rank_by {
+            RankBy::Skew => over(a)
+                .cmp(&over(b))
+                .then(a.max_to_mean.total_cmp(&b.max_to_mean)),
+            RankBy::Load => over(a)
+                .cmp(&over(b))
+                .then(a.max_load.total_cmp(&b.max_load)),
+            RankBy::Moved => a
+                .load_moved
+                .unwrap_or(0.0)
+                .total_cmp(&b.load_moved.unwrap_or(0.0)),
+        };
+        order.then(a.partition_count.cmp(&b.partition_count))
+    });
+}
+
+pub fn run(args: &RecommendArgs) -> Result<(), String> {
+    if args.from > args.to {
+        return Err(format!(
+            "--from {} is larger than --to {}",
+            args.from, args.to
+        ));
+    }
+    if args.rank_by == RankBy::Moved && args.current.is_none() {
+        return Err("ranking by moved keys needs the --current partition count".to_string());
+    }
+    for count in [Some(args.to), args.current].into_iter().flatten() {
+        args.scheme.check_partition_count(count)?;
+    }
+
+    let (weights, skipped_lines) = read_key_files(&args.files, &args.fields)?;
+    let sample = Sample::new(weights, skipped_lines);
+    if sample.len() == 0 {
+        return Err("the sample has no keys".to_string());
+    }
+
+    let current = args
+        .current
+        .map(|current| sample.place(args.scheme, current));
+    let mut candidates: Vec<Candidate> = (args.from..=args.to)
+        .into_par_iter()
+        .map(|count| sample.evaluate(args.scheme, count, current.as_ref(), args.limit))
+        .collect();
+    rank(&mut candidates, args.rank_by);
+    candidates.truncate(args.top.unwrap_or(candidates.len()));
+
+    if args.json {
+        return print_json(&candidates);
+    }
+    println!(
+        "{} distinct keys, {} lines skipped, ranked by {}",
+        sample.len(),
+        sample.skipped_lines,
+        args.rank_by.to_possible_value().unwrap().get_name()
+    );
+    println!();
+    print_markdown(&candidates, args.current);
+    Ok(())
+}
+
+fn print_markdown(candidates: &[Candidate], current: Option<i16>) {
+    let moved_header = match current {
+        Some(current) => format!("keys moved from {current}"),
+        None => "keys moved".to_string(),
+    };
+    println!(
+        "| rank | partitions | max/mean | std/mean | max load | utilization | empty | {moved_header} | load moved |"
+    );
+    println!("|---:|---:|---:|---:|---:|---:|---:|---:|---:|");
+    let or_dash = |value: Option<String>| value.unwrap_or_else(|| "-".to_string());
+    for (rank, candidate) in candidates.iter().enumerate() {
+        println!(
+            "| {} | {} | {:.3} | {:.3} | {:.1} | {} | {} | {} | {} |",
+            rank + 1,
+            candidate.partition_count,
+            candidate.max_to_mean,
+            candidate.variation,
+            candidate.max_load,
+            or_dash(
+                candidate
+                    .utilization
+                    .map(|use_| format!("{:.0}%", 100.0 * use_))
+            ),
+            candidate.empty_partitions,
+            or_dash(candidate.keys_moved.map(|keys| keys.to_string())),
+            or_dash(
+                candidate
+                    .load_moved
+                    .map(|share| format!("{:.1}%", 100.0 * share))
+            ),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::get_partition_id;
+    use crate::records::read_weights;
+    use crate::records::Field;
+
+    #[test]
+    fn test_candidates_are_evaluated_and_ranked() -> Result<(), String> {
+        let mut weights = HashMap::new();
+        // Fractional weights, such as messages per second, add up like whole ones.
+        let input = "a\t10\nb\t0.5\nc\t1\nd\t1.5\na\t10\nbroken\n";
+        let skipped = read_weights(
+            input.as_bytes(),
+            &RecordFields {
+                key_field: Field::Column(1),
+                weight_field: Some(Field::Column(2)),
+            },
+            &mut weights,
+        )?;
+        assert_eq!(skipped, 1);
+        assert_eq!((weights["a"], weights["b"]), (20.0, 0.5));
+        let sample = Sample::new(weights, skipped);
+
+        let current = sample.place(Scheme::ServiceBus, 2);
+        let single = sample.evaluate(Scheme::ServiceBus, 1, Some(&current), Some(20.0));
+        assert_eq!((single.max_to_mean, single.max_load), (1.0, 23.0));
+        assert_eq!(single.utilization, Some(1.15));
+        let moved: Vec<&str> = ["a", "b", "c", "d"]
+            .into_iter()
+            .filter(|key| get_partition_id(2, key) != 0)
+            .collect();
+        assert_eq!(single.keys_moved, Some(moved.len() as u64));
+
+        let mut candidates: Vec<Candidate> = (1..=4)
+            .map(|count| sample.evaluate(Scheme::ServiceBus, count, None, Some(20.0)))
+            .collect();
+        rank(&mut candidates, RankBy::Load);
+        assert!(candidates
+            .windows(2)
+            .all(|pair| pair[0].max_load <= pair[1].max_load));
+        assert_eq!(candidates[3].partition_count, 1);
+
+        // A single partition is perfectly even but over the limit, so it ranks last.
+        let mut candidates: Vec<Candidate> = (1..=4)
+            .map(|count| sample.evaluate(Scheme::ServiceBus, count, None, Some(22.5)))
+            .collect();
+        rank(&mut candidates, RankBy::Skew);
+        let order: Vec<i16> = candidates.iter().map(|c| c.partition_count).collect();
+        assert_eq!(order, [2, 3, 4, 1]);
+        assert_eq!(RankBy::default(), RankBy::Load);
+        Ok(())
+    }
+}
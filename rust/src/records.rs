@@ -0,0 +1,179 @@
+//! Key records of the analysis modes: a partition key and the weight the record stands for.
+//!
+//! A field is either a tab-separated column (`2`) or the name of a field of an NDJSON object
+//! (`tenantId`, `body.tenantId` for nested objects). Weights are non-negative numbers, e.g.
+//! message counts, byte volumes or messages per second, so pre-aggregated `key<TAB>count`
+//! tables can be read directly.
+
+use clap::Args;
+use serde_json::Value;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader};
+use std::path::PathBuf;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Field {
+    /// 1-based tab-separated column
+    Column(usize),
+    /// JSON pointer into an NDJSON record, e.g. `/body/tenantId`
+    Json(String),
+}
+
+impl FromStr for Field {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() {
+            return Err("empty field name".to_string());
+        }
+        if s.bytes().all(|b| b.is_ascii_digit()) {
+            return match s.parse() {
+                Ok(0) | Err(_) => Err(format!("invalid column {s}, columns start at 1")),
+                Ok(column) => Ok(Field::Column(column)),
+            };
+        }
+        let pointer = s
+            .split('.')
+            .map(|part| part.replace('~', "~0").replace('/', "~1"))
+            .collect::<Vec<_>>()
+            .join("/");
+        Ok(Field::Json(format!("/{pointer}")))
+    }
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct RecordFields {
+    /// Field holding the partition key: a tab-separated column (1-based), or the name of a
+    /// JSON field for NDJSON input
+    #[clap(long, default_value = "1")]
+    pub key_field: Field,
+
+    /// Field holding the weight of a record, a message count, a byte volume or a rate such as
+    /// messages per second; every record weighs 1 otherwise
+    #[clap(long)]
+    pub weight_field: Option<Field>,
+}
+
+impl Default for RecordFields {
+    fn default() -> Self {
+        RecordFields {
+            key_field: Field::Column(1),
+            weight_field: None,
+        }
+    }
+}
+
+impl RecordFields {
+    /// The key and the weight of a line, `None` for lines that do not have both; empty keys
+    /// and negative or non-finite weights count as missing.
+    pub fn parse<'a>(&self, line: &'a str) -> Option<(Cow<'a, str>, f64)> {
+        let uses_json = matches!(self.key_field, Field::Json(_))
+            || matches!(self.weight_field, Some(Field::Json(_)));
+        let json = if uses_json {
+            serde_json::from_str::<Value>(line).ok()
+        } else {
+            None
+        };
+        let lookup = |field: &Field| -> Option<Cow<'a, str>> {
+            match field {
+                Field::Column(column) => line.split('\t').nth(column - 1).map(Cow::Borrowed),
+                Field::Json(pointer) => match json.as_ref()?.pointer(pointer)? {
+                    Value::String(text) => Some(Cow::Owned(text.clone())),
+                    Value::Number(number) => Some(Cow::Owned(number.to_string())),
+                    _ => None,
+                },
+            }
+        };
+
+        let key = lookup(&self.key_field).filter(|key| !key.is_empty())?;
+        let weight = match &self.weight_field {
+            Some(field) => lookup(field)?
+                .trim()
+                .parse::<f64>()
+                .ok()
+                .filter(|weight| weight.is_finite() && *weight >= 0.0)
+                // `-0` becomes `+0`, whose bits order below every positive weight
+                .map(|weight| weight + 0.0)?,
+            None => 1.0,
+        };
+        Some((key, weight))
+    }
+}
+
+/// Sums the weights of every distinct key of `reader` into `weights`; returns the number of
+/// skipped lines.
+pub fn read_weights(
+    reader: impl BufRead,
+    fields: &RecordFields,
+    weights: &mut HashMap<String, f64>,
+) -> Result<u64, String> {
+    let mut skipped_lines = 0;
+    for line in reader.lines() {
+        let line = line.map_err(|e| e.to_string())?;
+        match fields.parse(&line) {
+            Some((key, weight)) => *weights.entry(key.into_owned()).or_default() += weight,
+            None => skipped_lines += 1,
+        }
+    }
+    Ok(skipped_lines)
+}
+
+/// The summed weights of every distinct key of `files`, or of stdin when there are none,
+/// and the number of skipped lines.
+pub fn read_key_files(
+    files: &[PathBuf],
+    fields: &RecordFields,
+) -> Result<(HashMap<String, f64>, u64), String> {
+    let mut weights = HashMap::new();
+    let mut skipped_lines = 0;
+    for path in files {
+        let file = std::fs::File::open(path)
+            .map_err(|e| format!("cannot open {}: {e}", path.display()))?;
+        skipped_lines += read_weights(BufReader::new(file), fields, &mut weights)
+            .map_err(|e| format!("cannot read {}: {e}", path.display()))?;
+    }
+    if files.is_empty() {
+        skipped_lines = read_weights(std::io::stdin().lock(), fields, &mut weights)?;
+    }
+    Ok((weights, skipped_lines))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_columns_and_json_fields() -> Result<(), String> {
+        let tsv = RecordFields {
+            key_field: "2".parse()?,
+            weight_field: Some("3".parse()?),
+        };
+        assert_eq!(tsv.parse("x\torder-1\t42"), Some(("order-1".into(), 42.0)));
+        assert_eq!(tsv.parse("x\torder-1\t4.5"), Some(("order-1".into(), 4.5)));
+        assert_eq!(tsv.parse("x\torder-1\t-1"), None);
+        assert_eq!(tsv.parse("x\torder-1\tNaN"), None);
+        let zero = tsv
+            .parse("x\torder-1\t-0")
+            .map(|(_, weight)| weight.to_bits());
+        assert_eq!(zero, Some(0.0f64.to_bits()));
+        assert_eq!(tsv.parse("x\t\t42"), None);
+        assert_eq!(
+            RecordFields::default().parse("k\t7"),
+            Some(("k".into(), 1.0))
+        );
+
+        let json = RecordFields {
+            key_field: "body.tenant".parse()?,
+            weight_field: Some("bytes".parse()?),
+        };
+        let line = r#"{"body": {"tenant": 17}, "bytes": 1024}"#;
+        assert_eq!(json.parse(line), Some(("17".into(), 1024.0)));
+        assert_eq!(json.parse(r#"{"body": {}, "bytes": 1}"#), None);
+        assert_eq!(json.parse("not json"), None);
+
+        assert!("0".parse::<Field>().is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,452 @@
+//! Redis Cluster hash slots: CRC16-XMODEM of the key (or of its `{hash tag}`) modulo 16384,
+//! and slot-to-node maps, the Redis counterpart of the logical partitions and range table.
+
+use clap::Args;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+
+use crate::analyze::format_weight;
+use crate::partitioner::Partitioner;
+use crate::print_json;
+use crate::records::{read_key_files, RecordFields};
+
+pub const SLOT_COUNT: u16 = 16384;
+
+/// Slots from the Redis cluster tutorial and the `CLUSTER KEYSLOT` documentation.
+pub const SLOT_VECTORS: &[(&str, u16)] = &[
+    ("123456789", 0x31c3 % SLOT_COUNT),
+    ("foo", 12182),
+    ("bar", 5061),
+    ("hello", 866),
+    ("somekey", 11058),
+    ("foo{hash_tag}", 2515),
+    ("bar{hash_tag}", 2515),
+];
+
+/// CRC16-XMODEM (CCITT polynomial 0x1021, initial value 0), as in Redis' `crc16.c`.
+pub fn crc16(data: &[u8]) -> u16 {
+    let mut crc = 0u16;
+    for byte in data {
+        crc ^= (*byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            };
+        }
+    }
+    crc
+}
+
+/// The part of a key that Redis hashes: the bytes between the first `{` and the next `}`
+/// when that is not empty, otherwise the whole key.
+pub fn hash_tag(key: &[u8]) -> &[u8] {
+    if let Some(open) = key.iter().position(|byte| *byte == b'{') {
+        if let Some(length) = key[open + 1..].iter().position(|byte| *byte == b'}') {
+            if length > 0 {
+                return &key[open + 1..open + 1 + length];
+            }
+        }
+    }
+    key
+}
+
+pub fn key_slot(key: &[u8]) -> u16 {
+    crc16(hash_tag(key)) % SLOT_COUNT
+}
+
+/// Slot maps from `--slot-map`, by node count.
+static CUSTOM_SLOT_MAPS: OnceLock<HashMap<i16, CustomSlotMap>> = OnceLock::new();
+
+#[derive(Debug)]
+struct CustomSlotMap {
+    path: PathBuf,
+    map: SlotMap,
+    /// Whether any mode asked for the map's node count
+    used: AtomicBool,
+}
+
+/// Slot ranges and the node that owns each of them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SlotMap {
+    /// Node names; a node's position here is its partition id.
+    pub nodes: Vec<String>,
+    /// Last slot of every range, ascending, ending with 16383.
+    ends: Vec<u16>,
+    /// Owning node of every range.
+    owners: Vec<u16>,
+}
+
+impl SlotMap {
+    /// Slots split into contiguous ranges the way `redis-cli --cluster create` assigns them to
+    /// `node_count` primaries; the nodes are named `0`, `1`, ...
+    pub fn even(node_count: i16) -> Self {
+        let per_node = SLOT_COUNT as f64 / node_count as f64;
+        let mut ends = Vec::with_capacity(node_count as usize);
+        let mut cursor = 0.0;
+        for node in 0..node_count {
+            let last = (cursor + per_node - 1.0).round() as u16;
+            ends.push(if node == node_count - 1 {
+                SLOT_COUNT - 1
+            } else {
+                last.min(SLOT_COUNT - 1)
+            });
+            cursor += per_node;
+        }
+        SlotMap {
+            nodes: (0..node_count).map(|node| node.to_string()).collect(),
+            ends,
+            owners: (0..node_count as u16).collect(),
+        }
+    }
+
+    /// Parses `first-last node` (or `slot node`) lines; `#` starts a comment. Every slot must be
+    /// assigned exactly once.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut ranges = Vec::new();
+        let mut nodes: Vec<String> = Vec::new();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let context = |message: String| format!("line {}: {message}", number + 1);
+            let mut fields = line.split_whitespace();
+            let (Some(slots), Some(node), None) = (fields.next(), fields.next(), fields.next())
+            else {
+                return Err(context(format!("expected `first-last node`, got {line:?}")));
+            };
+            let slot = |text: &str| -> Result<u16, String> {
+                text.parse::<u16>()
+                    .ok()
+                    .filter(|slot| *slot < SLOT_COUNT)
+                    .ok_or_else(|| context(format!("{text:?} is not a slot")))
+            };
+            let (first, last) = match slots.split_once('-') {
+                Some((first, last)) => (slot(first)?, slot(last)?),
+                None => (slot(slots)?, slot(slots)?),
+            };
+            if first > last {
+                return Err(context(format!("empty slot range {slots}")));
+            }
+            let owner = match nodes.iter().position(|name| name == node) {
+                Some(owner) => owner,
+                None => {
+                    nodes.push(node.to_string());
+                    nodes.len() - 1
+                }
+            };
+            ranges.push((first, last, owner as u16));
+        }
+        ranges.sort_unstable();
+
+        let mut next = 0u16;
+        for (first, last, _) in &ranges {
+            match first.cmp(&next) {
+                std::cmp::Ordering::Less => return Err(format!("slot {first} is assigned twice")),
+                std::cmp::Ordering::Greater => {
+                    return Err(format!("slots {next}-{} are not assigned", first - 1))
+                }
+                std::cmp::Ordering::Equal => next = last + 1,
+            }
+        }
+        if next != SLOT_COUNT {
+            return Err(format!("slots {next}-{} are not assigned", SLOT_COUNT - 1));
+        }
+        Ok(SlotMap {
+            nodes,
+            ends: ranges.iter().map(|(_, last, _)| *last).collect(),
+            owners: ranges.iter().map(|(_, _, owner)| *owner).collect(),
+        })
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| format!("cannot read {}: {e}", path.display()))?;
+        SlotMap::parse(&text).map_err(|e| format!("{}: {e}", path.display()))
+    }
+
+    pub fn node_of_slot(&self, slot: u16) -> u16 {
+        self.owners[self.ends.partition_point(|end| *end < slot)]
+    }
+}
+
+/// Makes the Redis scheme use the slot maps in `paths` instead of the even split for their
+/// node counts in every mode. Call once, before anything maps keys to partitions.
+pub fn install_slot_maps(paths: &[PathBuf]) -> Result<(), String> {
+    let mut maps = HashMap::new();
+    for path in paths {
+        let map = SlotMap::load(path)?;
+        let count = map.nodes.len() as i16;
+        let custom = CustomSlotMap {
+            path: path.clone(),
+            map,
+            used: AtomicBool::new(false),
+        };
+        if maps.insert(count, custom).is_some() {
+            return Err(format!(
+                "{}: there is already a slot map for {count} nodes",
+                path.display()
+            ));
+        }
+    }
+    CUSTOM_SLOT_MAPS
+        .set(maps)
+        .map_err(|_| "the slot maps are already installed".to_string())
+}
+
+/// The slot map for a node count: a map from `--slot-map` with that many nodes when there is
+/// one, the `redis-cli` split of [`SlotMap::even`] otherwise.
+pub fn slot_map(node_count: i16) -> SlotMap {
+    match CUSTOM_SLOT_MAPS
+        .get()
+        .and_then(|maps| maps.get(&node_count))
+    {
+        Some(custom) => {
+            custom.used.store(true, Ordering::Relaxed);
+            custom.map.clone()
+        }
+        None => SlotMap::even(node_count),
+    }
+}
+
+/// Fails for installed slot maps whose node count no mode asked for, since they would have
+/// been ignored silently.
+pub fn check_slot_maps_used() -> Result<(), String> {
+    unused_slot_maps(|_, custom| custom.used.load(Ordering::Relaxed))
+}
+
+/// The same check ahead of the run, for modes that know every node count they will ask for.
+pub fn check_slot_maps_cover(node_counts: &[i16]) -> Result<(), String> {
+    unused_slot_maps(|count, _| node_counts.contains(&count))
+}
+
+fn unused_slot_maps(used: impl Fn(i16, &CustomSlotMap) -> bool) -> Result<(), String> {
+    let Some(maps) = CUSTOM_SLOT_MAPS.get() else {
+        return Ok(());
+    };
+    let mut unused: Vec<String> = maps
+        .iter()
+        .filter(|(count, custom)| !used(**count, custom))
+        .map(|(count, custom)| format!("{} ({count} nodes)", custom.path.display()))
+        .collect();
+    if unused.is_empty() {
+        return Ok(());
+    }
+    unused.sort();
+    Err(format!(
+        "slot map {} was not used: a map only applies to the Redis scheme with as many \
+         partitions as it has nodes",
+        unused.join(", ")
+    ))
+}
+
+/// Redis Cluster placement of keys on the nodes of a slot map.
+#[derive(Debug, Clone)]
+pub struct RedisCluster {
+    slots: SlotMap,
+}
+
+impl RedisCluster {
+    pub fn new(slots: SlotMap) -> Self {
+        RedisCluster { slots }
+    }
+}
+
+impl Partitioner for RedisCluster {
+    fn partition_count(&self) -> i16 {
+        self.slots.nodes.len() as i16
+    }
+
+    fn partition(&self, key: &[u8]) -> u16 {
+        self.slots.node_of_slot(key_slot(key))
+    }
+}
+
+#[derive(Args, Debug)]
+pub struct ReshardArgs {
+    /// Key files with one record per line (reads stdin when omitted)
+    files: Vec<PathBuf>,
+
+    /// Current slot map (`first-last node` lines)
+    #[clap(long)]
+    from: PathBuf,
+
+    /// Planned slot map
+    #[clap(long)]
+    to: PathBuf,
+
+    #[command(flatten)]
+    fields: RecordFields,
+
+    /// Print JSON instead of a table
+    #[clap(long)]
+    json: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Reshard {
+    pub keys: u64,
+    pub weight: f64,
+    pub skipped_lines: u64,
+    pub moved_keys: u64,
+    pub moved_weight: f64,
+    pub moved_slots: u64,
+    pub nodes: Vec<NodeLoad>,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+pub struct NodeLoad {
+    pub node: String,
+    pub slots_before: u64,
+    pub slots_after: u64,
+    pub weight_before: f64,
+    pub weight_after: f64,
+    /// Weight of the keys that this node receives from other nodes
+    pub weight_in: f64,
+    /// Weight of the keys that this node hands to other nodes
+    pub weight_out: f64,
+}
+
+/// Where the keys of a sample go when the cluster moves from slot map `from` to `to`.
+pub fn reshard(weights: &HashMap<String, f64>, from: &SlotMap, to: &SlotMap) -> Reshard {
+    let mut names: Vec<String> = from.nodes.clone();
+    for node in &to.nodes {
+        if !names.contains(node) {
+            names.push(node.clone());
+        }
+    }
+    let index = |name: &String| names.iter().position(|node| node == name).unwrap();
+    let from_index: Vec<usize> = from.nodes.iter().map(index).collect();
+    let to_index: Vec<usize> = to.nodes.iter().map(index).collect();
+
+    let mut nodes: Vec<NodeLoad> = names
+        .iter()
+        .map(|node| NodeLoad {
+            node: node.clone(),
+            slots_before: 0,
+            slots_after: 0,
+            weight_before: 0.0,
+            weight_after: 0.0,
+            weight_in: 0.0,
+            weight_out: 0.0,
+        })
+        .collect();
+    let mut moved_slots = 0;
+    for slot in 0..SLOT_COUNT {
+        let before = from_index[from.node_of_slot(slot) as usize];
+        let after = to_index[to.node_of_slot(slot) as usize];
+        nodes[before].slots_before += 1;
+        nodes[after].slots_after += 1;
+        moved_slots += (before != after) as u64;
+    }
+
+    let (mut moved_keys, mut moved_weight) = (0, 0.0);
+    for (key, weight) in weights {
+        let slot = key_slot(key.as_bytes());
+        let before = from_index[from.node_of_slot(slot) as usize];
+        let after = to_index[to.node_of_slot(slot) as usize];
+        nodes[before].weight_before += weight;
+        nodes[after].weight_after += weight;
+        if before != after {
+            moved_keys += 1;
+            moved_weight += weight;
+            nodes[before].weight_out += weight;
+            nodes[after].weight_in += weight;
+        }
+    }
+    Reshard {
+        keys: weights.len() as u64,
+        weight: weights.values().sum(),
+        skipped_lines: 0,
+        moved_keys,
+        moved_weight,
+        moved_slots,
+        nodes,
+    }
+}
+
+pub fn run(args: &ReshardArgs) -> Result<(), String> {
+    let (from, to) = (SlotMap::load(&args.from)?, SlotMap::load(&args.to)?);
+    let (weights, skipped_lines) = read_key_files(&args.files, &args.fields)?;
+    let mut plan = reshard(&weights, &from, &to);
+    plan.skipped_lines = skipped_lines;
+    if args.json {
+        return print_json(&plan);
+    }
+
+    println!(
+        "{:>16} {:>8} {:>8} {:>10} {:>10} {:>10} {:>10}",
+        "node", "slots", "→ slots", "weight", "→ weight", "in", "out"
+    );
+    for node in &plan.nodes {
+        println!(
+            "{:>16} {:>8} {:>8} {:>10} {:>10} {:>10} {:>10}",
+            node.node,
+            node.slots_before,
+            node.slots_after,
+            format_weight(node.weight_before),
+            format_weight(node.weight_after),
+            format_weight(node.weight_in),
+            format_weight(node.weight_out)
+        );
+    }
+    println!(
+        "{} slots, {} of {} keys and {} of {} weight move",
+        plan.moved_slots,
+        plan.moved_keys,
+        plan.keys,
+        format_weight(plan.moved_weight),
+        format_weight(plan.weight)
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_slots() {
+        for (key, slot) in SLOT_VECTORS {
+            assert_eq!(key_slot(key.as_bytes()), *slot, "{key}");
+        }
+        assert_eq!(crc16(b"123456789"), 0x31c3);
+        // Edge cases from the hash tags section of the cluster specification.
+        assert_eq!(hash_tag(b"{user1000}.following"), b"user1000");
+        assert_eq!(hash_tag(b"foo{}{bar}"), b"foo{}{bar}");
+        assert_eq!(hash_tag(b"foo{{bar}}zap"), b"{bar");
+        assert_eq!(hash_tag(b"foo{bar}{zap}"), b"bar");
+    }
+
+    #[test]
+    fn test_slot_maps() -> Result<(), String> {
+        // redis-cli assigns 0-5460, 5461-10922 and 10923-16383 to three primaries.
+        let even = SlotMap::even(3);
+        assert_eq!(even.ends, vec![5460, 10922, 16383]);
+        assert_eq!(
+            even,
+            SlotMap::parse("0-5460 0\n5461-10922 1\n10923-16383 2\n")?
+        );
+
+        let moved = SlotMap::parse("0-5460 0\n5461-10922 1 # moved below\n10923-16383 2\n")?;
+        assert_eq!(RedisCluster::new(moved.clone()).partition(b"foo"), 2);
+        assert!(SlotMap::parse("0-5460 a\n5460-16383 b").is_err());
+        assert!(SlotMap::parse("0-5460 a\n5462-16383 b").is_err());
+
+        let weights: HashMap<String, f64> = [("foo", 5.0), ("bar", 3.0)]
+            .into_iter()
+            .map(|(key, weight)| (key.to_string(), weight))
+            .collect();
+        let four = SlotMap::parse("0-5460 0\n5461-10922 1\n10923-12000 2\n12001-16383 3\n")?;
+        let plan = reshard(&weights, &moved, &four);
+        assert_eq!(plan.moved_slots, 16383 - 12000);
+        assert_eq!((plan.moved_keys, plan.moved_weight), (1, 5.0));
+        assert_eq!(plan.nodes[3].weight_in, 5.0);
+        Ok(())
+    }
+}
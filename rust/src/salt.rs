@@ -0,0 +1,227 @@
+use clap::Args;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::partitioner::{Partitioner, Scheme};
+use crate::print_json;
+
+const DEFAULT_MAX_SALT: u64 = 1_000_000;
+
+#[derive(Args, Debug)]
+pub struct SaltArgs {
+    /// The hot key to spread out
+    base_key: String,
+
+    /// Number of partitions
+    #[clap(short = 'c', long, value_parser = clap::value_parser!(i16).range(1..))]
+    partition_count: i16,
+
+    /// Partitioning scheme that places the keys
+    #[clap(long, value_enum, default_value_t)]
+    scheme: Scheme,
+
+    /// How a salted key is built; `{key}` is the base key, `{n}` or `{n:04}` the salt
+    #[clap(short = 'f', long, default_value = "{key}#{n}")]
+    format: SaltFormat,
+
+    /// Only cover these partitions, e.g. `--partitions 0,3,5` (all partitions when omitted)
+    #[clap(short = 'p', long, value_delimiter = ',')]
+    partitions: Vec<u16>,
+
+    /// Largest salt to try before giving up
+    #[clap(long, default_value_t = DEFAULT_MAX_SALT)]
+    max_salt: u64,
+
+    /// Print JSON instead of a table
+    #[clap(long)]
+    json: bool,
+}
+
+/// A salted-key template like `{key}#{n}` or `{n:03}-{key}`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SaltFormat {
+    parts: Vec<Part>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Part {
+    Literal(String),
+    Key,
+    Salt { width: usize },
+}
+
+impl std::str::FromStr for SaltFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = Vec::new();
+        let mut rest = s;
+        while let Some(open) = rest.find('{') {
+            if open > 0 {
+                parts.push(Part::Literal(rest[..open].to_string()));
+            }
+            let close = rest[open..]
+                .find('}')
+                .ok_or_else(|| format!("missing `}}` in salt format {s:?}"))?
+                + open;
+            parts.push(match &rest[open + 1..close] {
+                "key" => Part::Key,
+                "n" => Part::Salt { width: 0 },
+                placeholder => match placeholder.strip_prefix("n:0") {
+                    Some(width) => Part::Salt {
+                        width: width
+                            .parse()
+                            .map_err(|_| format!("invalid salt width in {{{placeholder}}}"))?,
+                    },
+                    None => return Err(format!("unknown placeholder {{{placeholder}}}")),
+                },
+            });
+            rest = &rest[close + 1..];
+        }
+        if !rest.is_empty() {
+            parts.push(Part::Literal(rest.to_string()));
+        }
+        if !parts.iter().any(|part| matches!(part, Part::Salt { .. })) {
+            return Err(format!("salt format {s:?} has no {{n}} placeholder"));
+        }
+        Ok(SaltFormat { parts })
+    }
+}
+
+impl SaltFormat {
+    pub fn render(&self, base_key: &str, salt: u64) -> String {
+        let mut key = String::new();
+        for part in &self.parts {
+            match part {
+                Part::Literal(text) => key.push_str(text),
+                Part::Key => key.push_str(base_key),
+                Part::Salt { width } => key.push_str(&format!("{salt:0width$}")),
+            }
+        }
+        key
+    }
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+pub struct SaltedKey {
+    pub salt: u64,
+    pub key: String,
+    pub partition_id: u16,
+}
+
+pub fn run(args: &SaltArgs) -> Result<(), String> {
+    args.scheme.check_partition_count(args.partition_count)?;
+    let wanted: Vec<u16> = if args.partitions.is_empty() {
+        (0..args.partition_count as u16).collect()
+    } else {
+        args.partitions.clone()
+    };
+    if let Some(missing) = wanted
+        .iter()
+        .find(|id| **id as i32 >= args.partition_count as i32)
+    {
+        return Err(format!(
+            "partition {missing} does not exist with {} partitions",
+            args.partition_count
+        ));
+    }
+
+    let table = plan_salts(
+        &args.base_key,
+        &args.format,
+        args.scheme.partitioner(args.partition_count).as_ref(),
+        &wanted,
+        args.max_salt,
+    )?;
+
+    if args.json {
+        print_json(&table)
+    } else {
+        println!("{:>8} {:>12}  key", "salt", "partition_id");
+        for salted in &table {
+            println!(
+                "{:>8} {:>12}  {}",
+                salted.salt, salted.partition_id, salted.key
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Tries salts 0, 1, 2, ... and keeps the first salt that lands in each wanted partition,
+/// which gives one salted key per partition with the smallest salt numbers possible.
+pub fn plan_salts(
+    base_key: &str,
+    format: &SaltFormat,
+    partitioner: &dyn Partitioner,
+    wanted: &[u16],
+    max_salt: u64,
+) -> Result<Vec<SaltedKey>, String> {
+    let mut open: BTreeSet<u16> = wanted.iter().copied().collect();
+    let mut found = BTreeMap::new();
+    for salt in 0..=max_salt {
+        if open.is_empty() {
+            break;
+        }
+        let key = format.render(base_key, salt);
+        let partition_id = partitioner.partition_of(&key);
+        if open.remove(&partition_id) {
+            found.insert(
+                partition_id,
+                SaltedKey {
+                    salt,
+                    key,
+                    partition_id,
+                },
+            );
+        }
+    }
+
+    if !open.is_empty() {
+        let missing: Vec<String> = open.iter().map(u16::to_string).collect();
+        return Err(format!(
+            "no salt up to {max_salt} reaches partition(s) {}",
+            missing.join(", ")
+        ));
+    }
+    let mut table: Vec<SaltedKey> = found.into_values().collect();
+    table.sort_by_key(|salted| salted.salt);
+    Ok(table)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::get_partition_id;
+
+    #[test]
+    fn test_salts_cover_every_partition_once() -> Result<(), String> {
+        let format: SaltFormat = "{key}#{n}".parse()?;
+        let wanted: Vec<u16> = (0..8).collect();
+        let table = plan_salts(
+            "tenant-42",
+            &format,
+            Scheme::ServiceBus.partitioner(8).as_ref(),
+            &wanted,
+            10_000,
+        )?;
+
+        let mut partitions: Vec<u16> = table.iter().map(|salted| salted.partition_id).collect();
+        partitions.sort_unstable();
+        assert_eq!(partitions, wanted);
+        for salted in &table {
+            assert_eq!(salted.key, format!("tenant-42#{}", salted.salt));
+            assert_eq!(get_partition_id(8, &salted.key), salted.partition_id);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_salt_format() -> Result<(), String> {
+        let format: SaltFormat = "{n:03}-{key}".parse()?;
+        assert_eq!(format.render("hot", 7), "007-hot");
+        assert!("{key}".parse::<SaltFormat>().is_err());
+        assert!("{key}#{m}".parse::<SaltFormat>().is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,199 @@
+use clap::Args;
+
+use crate::consistent::{jump_hash, JUMP_VECTORS};
+use crate::cosmos::{effective_partition_key, parse_partition_key, EPK_VECTORS};
+use crate::hash_seeded;
+use crate::kafka::{murmur2, MURMUR2_VECTORS};
+use crate::redis::{key_slot, SLOT_VECTORS};
+use crate::verify::{check_vectors, mismatch_report, parse_vectors, SERVICE_BUS_VECTORS};
+
+/// Non-ASCII keys with the partitions the .NET reference (`dotnet run -- vectors`) assigns them.
+pub const NON_ASCII_VECTORS: &str = include_str!("../vectors/non_ascii.tsv");
+
+/// `hashlittle2` results printed by `driver5()` in Bob Jenkins' lookup3.c.
+#[rustfmt::skip]
+const LOOKUP3_VECTORS: &[(&str, u32, u32, u32, u32)] = &[
+    // (input, pc, pb, expected c, expected b)
+    ("",                               0,          0,          0xdeadbeef, 0xdeadbeef),
+    ("",                               0,          0xdeadbeef, 0xbd5b7dde, 0xdeadbeef),
+    ("",                               0xdeadbeef, 0xdeadbeef, 0x9c093ccd, 0xbd5b7dde),
+    ("Four score and seven years ago", 0,          0,          0x17770551, 0xce7226e6),
+    ("Four score and seven years ago", 0,          1,          0xe3607cae, 0xbd371de4),
+    ("Four score and seven years ago", 1,          0,          0xcd628161, 0x6cbea4b3),
+];
+
+#[derive(Args, Debug)]
+pub struct SelfTestArgs {}
+
+#[derive(Debug, Default)]
+pub struct Outcome {
+    pub passed: usize,
+    pub failed: usize,
+    /// Details of the failed vectors, indented for the summary
+    pub report: String,
+}
+
+pub fn run(_args: &SelfTestArgs) -> Result<(), String> {
+    println!(
+        "partition_id {} on {} ({}-endian)",
+        env!("CARGO_PKG_VERSION"),
+        std::env::consts::ARCH,
+        if cfg!(target_endian = "big") {
+            "big"
+        } else {
+            "little"
+        }
+    );
+
+    let groups = [
+        ("lookup3 reference vectors", check_lookup3()),
+        ("Kafka murmur2 reference vectors", check_murmur2()),
+        ("Cosmos DB effective partition keys", check_cosmos()),
+        ("Redis Cluster hash slots", check_redis_slots()),
+        ("Jump Consistent Hash vectors", check_jump_hash()),
+        (
+            "Service Bus partition vectors",
+            check_partition_vectors("service_bus.tsv", SERVICE_BUS_VECTORS)?,
+        ),
+        (
+            "non-ASCII .NET parity vectors",
+            check_partition_vectors("non_ascii.tsv", NON_ASCII_VECTORS)?,
+        ),
+    ];
+
+    let mut passed = 0;
+    let mut failed = 0;
+    for (name, outcome) in &groups {
+        let total = outcome.passed + outcome.failed;
+        let status = if outcome.failed == 0 { "PASS" } else { "FAIL" };
+        println!("{status} {name}: {}/{total}", outcome.passed);
+        print!("{}", outcome.report);
+        passed += outcome.passed;
+        failed += outcome.failed;
+    }
+
+    if failed > 0 {
+        return Err(format!(
+            "self-test failed: {failed} of {} vectors do not match",
+            passed + failed
+        ));
+    }
+    println!("self-test passed: {passed} vectors");
+    Ok(())
+}
+
+/// Runs every case through `mismatch`, which describes the cases that fail and returns
+/// `None` for the ones that pass.
+pub fn check<T>(cases: &[T], mismatch: impl Fn(&T) -> Option<String>) -> Outcome {
+    let mut outcome = Outcome::default();
+    for case in cases {
+        match mismatch(case) {
+            None => outcome.passed += 1,
+            Some(description) => {
+                outcome.failed += 1;
+                outcome.report.push_str(&format!("  {description}\n"));
+            }
+        }
+    }
+    outcome
+}
+
+pub fn check_lookup3() -> Outcome {
+    check(
+        LOOKUP3_VECTORS,
+        |&(input, pc, pb, expected_c, expected_b)| {
+            let (c, b, _) = hash_seeded(input.as_bytes(), pc, pb);
+            ((c, b) != (expected_c, expected_b)).then(|| {
+                format!(
+                    "hashlittle2({input:?}, pc={pc:#x}, pb={pb:#x}): \
+                 expected {expected_c:08x} {expected_b:08x}, got {c:08x} {b:08x}"
+                )
+            })
+        },
+    )
+}
+
+pub fn check_murmur2() -> Outcome {
+    check(MURMUR2_VECTORS, |&(input, expected)| {
+        let actual = murmur2(input);
+        (actual != expected).then(|| {
+            format!(
+                "murmur2({:?}): expected {expected}, got {actual}",
+                String::from_utf8_lossy(input)
+            )
+        })
+    })
+}
+
+pub fn check_cosmos() -> Outcome {
+    check(
+        EPK_VECTORS,
+        |&(key, version, expected)| match parse_partition_key(key) {
+            Ok(components) => {
+                let actual = effective_partition_key(&components, version);
+                (actual != expected)
+                    .then(|| format!("{key} (hash {version:?}): expected {expected}, got {actual}"))
+            }
+            Err(message) => Some(format!("{key}: {message}")),
+        },
+    )
+}
+
+pub fn check_redis_slots() -> Outcome {
+    check(SLOT_VECTORS, |&(key, expected)| {
+        let actual = key_slot(key.as_bytes());
+        (actual != expected).then(|| format!("slot of {key:?}: expected {expected}, got {actual}"))
+    })
+}
+
+pub fn check_jump_hash() -> Outcome {
+    check(JUMP_VECTORS, |&(key, buckets, expected)| {
+        let actual = jump_hash(key, buckets);
+        (actual != expected)
+            .then(|| format!("jump_hash({key:#x}, {buckets}): expected {expected}, got {actual}"))
+    })
+}
+
+pub fn check_partition_vectors(name: &str, text: &str) -> Result<Outcome, String> {
+    let vectors = parse_vectors(text).map_err(|e| format!("{name}: {e}"))?;
+    let mismatches = check_vectors(&vectors);
+    let report = if mismatches.is_empty() {
+        String::new()
+    } else {
+        mismatch_report(name, &mismatches)
+            .lines()
+            .map(|line| format!("  {line}\n"))
+            .collect()
+    };
+    Ok(Outcome {
+        passed: vectors.len() - mismatches.len(),
+        failed: mismatches.len(),
+        report,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_embedded_vectors_pass() -> Result<(), String> {
+        let lookup3 = check_lookup3();
+        assert_eq!(lookup3.failed, 0, "{}", lookup3.report);
+        assert_eq!(lookup3.passed, LOOKUP3_VECTORS.len());
+        assert_eq!(check_murmur2().failed, 0);
+        assert_eq!(check_cosmos().failed, 0);
+        assert_eq!(check_redis_slots().failed, 0);
+        assert_eq!(check_jump_hash().failed, 0);
+
+        let outcome = check(&[1, 2, 3], |case| {
+            (*case == 2).then(|| format!("case {case}"))
+        });
+        assert_eq!((outcome.passed, outcome.failed), (2, 1));
+        assert_eq!(outcome.report, "  case 2\n");
+
+        let non_ascii = check_partition_vectors("non_ascii.tsv", NON_ASCII_VECTORS)?;
+        assert_eq!(non_ascii.failed, 0, "{}", non_ascii.report);
+        Ok(())
+    }
+}
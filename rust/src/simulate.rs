@@ -0,0 +1,474 @@
+use clap::{ArgGroup, Args};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::io::{BufRead, BufReader};
+use std::path::PathBuf;
+
+use crate::partitioner::Scheme;
+use crate::print_json;
+
+#[derive(Args, Debug)]
+#[clap(group(ArgGroup::new("limits").required(true).multiple(true)))]
+pub struct SimulateArgs {
+    /// Message log with one `time<TAB>key<TAB>size` record per line (reads stdin when omitted);
+    /// times are seconds since the epoch or RFC 3339 timestamps
+    input: Option<PathBuf>,
+
+    /// Number of partitions
+    #[clap(short = 'c', long, value_parser = clap::value_parser!(i16).range(1..))]
+    partition_count: i16,
+
+    /// Partitioning scheme that places the keys
+    #[clap(long, value_enum, default_value_t)]
+    scheme: Scheme,
+
+    /// Tab-separated column (1-based) holding the timestamp
+    #[clap(long, default_value_t = 1, value_parser = clap::value_parser!(u16).range(1..))]
+    time_field: u16,
+
+    /// Tab-separated column (1-based) holding the partition key
+    #[clap(long, default_value_t = 2, value_parser = clap::value_parser!(u16).range(1..))]
+    key_field: u16,
+
+    /// Tab-separated column (1-based) holding the message size in bytes
+    #[clap(long, default_value_t = 3, value_parser = clap::value_parser!(u16).range(1..))]
+    size_field: u16,
+
+    /// Length of a time bucket in seconds; rates are averaged over one bucket
+    #[clap(short = 'b', long, default_value_t = 1.0)]
+    bucket: f64,
+
+    /// Messages per second a single partition accepts before it throttles
+    #[clap(long, group = "limits")]
+    max_rate: Option<f64>,
+
+    /// Bytes per second a single partition accepts before it throttles
+    #[clap(long, group = "limits")]
+    max_byte_rate: Option<f64>,
+
+    /// Print JSON instead of a table
+    #[clap(long)]
+    json: bool,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Limits {
+    pub max_rate: Option<f64>,
+    pub max_byte_rate: Option<f64>,
+}
+
+/// Messages and bytes per partition and time bucket of a message log.
+#[derive(Debug)]
+pub struct Simulation {
+    partition_count: usize,
+    bucket_seconds: f64,
+    /// Bucket number (time divided by the bucket length) to per-partition `(messages, bytes)`
+    buckets: BTreeMap<i64, Vec<(u64, u64)>>,
+    pub skipped_lines: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SimulationReport {
+    pub bucket_seconds: f64,
+    pub messages: u64,
+    pub bytes: u64,
+    pub skipped_lines: u64,
+    /// Start of the first and end of the last bucket, in seconds since the epoch
+    pub start: f64,
+    pub end: f64,
+    pub partitions: Vec<PartitionSummary>,
+    pub throttling: Vec<ThrottleInterval>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PartitionSummary {
+    pub partition_id: u16,
+    pub messages: u64,
+    pub bytes: u64,
+    pub peak_rate: f64,
+    pub peak_byte_rate: f64,
+    /// Highest rate seen relative to its limit, 1.0 being the limit
+    pub peak_utilization: f64,
+    pub throttled_seconds: f64,
+}
+
+/// Consecutive buckets in which a partition was over one of its limits.
+#[derive(Debug, Serialize, PartialEq)]
+pub struct ThrottleInterval {
+    pub partition_id: u16,
+    pub start: f64,
+    pub end: f64,
+    /// `rate`, `byte_rate` or both, whichever limits were exceeded at the peak
+    pub reason: &'static str,
+    pub peak_utilization: f64,
+}
+
+impl Simulation {
+    pub fn new(partition_count: usize, bucket_seconds: f64) -> Self {
+        Simulation {
+            partition_count,
+            bucket_seconds,
+            buckets: BTreeMap::new(),
+            skipped_lines: 0,
+        }
+    }
+
+    pub fn record(&mut self, time: f64, partition_id: u16, bytes: u64) {
+        let bucket = (time / self.bucket_seconds).floor() as i64;
+        let load = &mut self
+            .buckets
+            .entry(bucket)
+            .or_insert_with(|| vec![(0, 0); self.partition_count])[partition_id as usize];
+        load.0 += 1;
+        load.1 += bytes;
+    }
+
+    pub fn report(&self, limits: &Limits) -> SimulationReport {
+        let seconds = self.bucket_seconds;
+        let mut partitions: Vec<PartitionSummary> = (0..self.partition_count)
+            .map(|partition| PartitionSummary {
+                partition_id: partition as u16,
+                messages: 0,
+                bytes: 0,
+                peak_rate: 0.0,
+                peak_byte_rate: 0.0,
+                peak_utilization: 0.0,
+                throttled_seconds: 0.0,
+            })
+            .collect();
+        let mut throttling = Vec::new();
+        // The interval each partition is throttled in right now, with the bucket it ends after.
+        let mut open: Vec<Option<(ThrottleInterval, i64)>> =
+            (0..self.partition_count).map(|_| None).collect();
+
+        for (bucket, loads) in &self.buckets {
+            for (partition, (messages, bytes)) in loads.iter().enumerate() {
+                let summary = &mut partitions[partition];
+                summary.messages += messages;
+                summary.bytes += bytes;
+                let rate = *messages as f64 / seconds;
+                let byte_rate = *bytes as f64 / seconds;
+                summary.peak_rate = f64::max(summary.peak_rate, rate);
+                summary.peak_byte_rate = f64::max(summary.peak_byte_rate, byte_rate);
+
+                let rate_use = limits.max_rate.map_or(0.0, |limit| rate / limit);
+                let byte_rate_use = limits.max_byte_rate.map_or(0.0, |limit| byte_rate / limit);
+                let utilization = f64::max(rate_use, byte_rate_use);
+                summary.peak_utilization = f64::max(summary.peak_utilization, utilization);
+                if utilization <= 1.0 {
+                    continue;
+                }
+
+                summary.throttled_seconds += seconds;
+                let reason = match (rate_use > 1.0, byte_rate_use > 1.0) {
+                    (true, true) => "rate,byte_rate",
+                    (true, false) => "rate",
+                    _ => "byte_rate",
+                };
+                let start = *bucket as f64 * seconds;
+                match &mut open[partition] {
+                    Some((interval, last)) if last.checked_add(1) == Some(*bucket) => {
+                        interval.end = start + seconds;
+                        *last = *bucket;
+                        if utilization > interval.peak_utilization {
+                            interval.peak_utilization = utilization;
+                            interval.reason = reason;
+                        }
+                    }
+                    current => {
+                        if let Some((interval, _)) = current.take() {
+                            throttling.push(interval);
+                        }
+                        *current = Some((
+                            ThrottleInterval {
+                                partition_id: partition as u16,
+                                start,
+                                end: start + seconds,
+                                reason,
+                                peak_utilization: utilization,
+                            },
+                            *bucket,
+                        ));
+                    }
+                }
+            }
+        }
+        throttling.extend(open.into_iter().flatten().map(|(interval, _)| interval));
+        throttling.sort_by(|a, b| {
+            a.start
+                .total_cmp(&b.start)
+                .then(a.partition_id.cmp(&b.partition_id))
+        });
+
+        SimulationReport {
+            bucket_seconds: seconds,
+            messages: partitions.iter().map(|summary| summary.messages).sum(),
+            bytes: partitions.iter().map(|summary| summary.bytes).sum(),
+            skipped_lines: self.skipped_lines,
+            start: self
+                .buckets
+                .keys()
+                .next()
+                .map_or(0.0, |bucket| *bucket as f64 * seconds),
+            end: self
+                .buckets
+                .keys()
+                .next_back()
+                .map_or(0.0, |bucket| (*bucket as f64 + 1.0) * seconds),
+            partitions,
+            throttling,
+        }
+    }
+}
+
+/// Parses seconds since the epoch (`1714567890.25`) or an RFC 3339 timestamp
+/// (`2024-05-01T12:51:30.25Z`, `2024-05-01 14:51:30+02:00`).
+pub fn parse_time(text: &str) -> Option<f64> {
+    let text = text.trim();
+    if let Ok(seconds) = text.parse::<f64>() {
+        return seconds.is_finite().then_some(seconds);
+    }
+
+    let number = |range: std::ops::Range<usize>| text.get(range)?.parse::<i64>().ok();
+    let (year, month, day) = (number(0..4)?, number(5..7)?, number(8..10)?);
+    let (hour, minute, second) = (number(11..13)?, number(14..16)?, number(17..19)?);
+    let separators = text.as_bytes();
+    if separators[4] != b'-'
+        || separators[7] != b'-'
+        || !matches!(separators[10], b'T' | b't' | b' ')
+    {
+        return None;
+    }
+    if !(1..=12).contains(&month)
+        || !(1..=31).contains(&day)
+        || hour > 23
+        || minute > 59
+        || second > 60
+    {
+        return None;
+    }
+
+    let mut rest = &text[19..];
+    let mut fraction = 0.0;
+    if let Some(digits) = rest.strip_prefix('.') {
+        let length = digits.bytes().take_while(u8::is_ascii_digit).count();
+        fraction = format!("0.{}", &digits[..length]).parse().ok()?;
+        rest = &digits[length..];
+    }
+    let offset = match rest {
+        "Z" | "z" => 0,
+        _ => {
+            let sign = match rest.get(..1)? {
+                "+" => 1,
+                "-" => -1,
+                _ => return None,
+            };
+            let (hours, minutes) = rest[1..].split_once(':')?;
+            sign * (hours.parse::<i64>().ok()? * 3600 + minutes.parse::<i64>().ok()? * 60)
+        }
+    };
+
+    let days = days_from_civil(year, month, day);
+    let seconds = days * 86_400 + hour * 3600 + minute * 60 + second - offset;
+    Some(seconds as f64 + fraction)
+}
+
+/// Days since 1970-01-01 of a proleptic Gregorian date (Howard Hinnant's algorithm).
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Formats seconds since the epoch as an RFC 3339 UTC timestamp.
+pub fn format_time(seconds: f64) -> String {
+    let millis = (seconds * 1000.0).round() as i64;
+    let (days, millis_of_day) = (millis.div_euclid(86_400_000), millis.rem_euclid(86_400_000));
+    // The inverse of `days_from_civil`.
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    let second_of_day = millis_of_day / 1000;
+    let time = format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}",
+        second_of_day / 3600,
+        second_of_day / 60 % 60,
+        second_of_day % 60
+    );
+    match millis_of_day % 1000 {
+        0 => format!("{time}Z"),
+        millis => format!("{time}.{millis:03}Z"),
+    }
+}
+
+pub fn run(args: &SimulateArgs) -> Result<(), String> {
+    args.scheme.check_partition_count(args.partition_count)?;
+    if args.bucket.is_nan() || args.bucket <= 0.0 {
+        return Err(format!(
+            "bucket length must be positive, got {}",
+            args.bucket
+        ));
+    }
+    let partitioner = args.scheme.partitioner(args.partition_count);
+    let mut simulation = Simulation::new(partitioner.partition_count() as usize, args.bucket);
+
+    let reader: Box<dyn BufRead> = match &args.input {
+        Some(path) => Box::new(BufReader::new(
+            std::fs::File::open(path)
+                .map_err(|e| format!("cannot open {}: {e}", path.display()))?,
+        )),
+        None => Box::new(std::io::stdin().lock()),
+    };
+    for line in reader.lines() {
+        let line = line.map_err(|e| e.to_string())?;
+        let fields: Vec<&str> = line.split('\t').collect();
+        let field = |number: u16| fields.get(number as usize - 1).copied();
+        let record = field(args.time_field)
+            .and_then(parse_time)
+            .zip(field(args.key_field))
+            .zip(field(args.size_field).and_then(|size| size.trim().parse::<u64>().ok()));
+        match record {
+            Some(((time, key), bytes)) => {
+                simulation.record(time, partitioner.partition_of(key), bytes);
+            }
+            None => simulation.skipped_lines += 1,
+        }
+    }
+
+    let limits = Limits {
+        max_rate: args.max_rate,
+        max_byte_rate: args.max_byte_rate,
+    };
+    let report = simulation.report(&limits);
+    if args.json {
+        return print_json(&report);
+    }
+
+    println!(
+        "{} messages, {} bytes from {} to {} in {}s buckets, {} lines skipped",
+        report.messages,
+        report.bytes,
+        format_time(report.start),
+        format_time(report.end),
+        report.bucket_seconds,
+        report.skipped_lines
+    );
+    println!(
+        "{:>12} {:>10} {:>12} {:>10} {:>12} {:>8} {:>10}",
+        "partition_id", "messages", "bytes", "peak msg/s", "peak bytes/s", "peak use", "throttled"
+    );
+    for summary in &report.partitions {
+        println!(
+            "{:>12} {:>10} {:>12} {:>10.1} {:>12.1} {:>7.0}% {:>9}s",
+            summary.partition_id,
+            summary.messages,
+            summary.bytes,
+            summary.peak_rate,
+            summary.peak_byte_rate,
+            100.0 * summary.peak_utilization,
+            summary.throttled_seconds
+        );
+    }
+    if report.throttling.is_empty() {
+        println!("no partition would have been throttled");
+    }
+    for interval in &report.throttling {
+        println!(
+            "THROTTLED partition {} from {} to {}: {} at {:.0}% of the limit",
+            interval.partition_id,
+            format_time(interval.start),
+            format_time(interval.end),
+            interval.reason,
+            100.0 * interval.peak_utilization
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_throttling_intervals() {
+        let mut simulation = Simulation::new(2, 1.0);
+        for second in 0..6 {
+            // Partition 0 bursts in seconds 1-2 and again in second 4.
+            let messages = if matches!(second, 1 | 2 | 4) { 15 } else { 5 };
+            for _ in 0..messages {
+                simulation.record(second as f64 + 0.5, 0, 100);
+            }
+            simulation.record(second as f64, 1, 5000);
+        }
+        let limits = Limits {
+            max_rate: Some(10.0),
+            max_byte_rate: Some(4000.0),
+        };
+        let report = simulation.report(&limits);
+
+        assert_eq!(report.messages, 66);
+        assert_eq!((report.start, report.end), (0.0, 6.0));
+        assert_eq!(report.partitions[0].peak_rate, 15.0);
+        assert_eq!(report.partitions[0].throttled_seconds, 3.0);
+        assert_eq!(report.partitions[1].peak_utilization, 1.25);
+        let intervals: Vec<(u16, f64, f64, &str)> = report
+            .throttling
+            .iter()
+            .map(|interval| {
+                (
+                    interval.partition_id,
+                    interval.start,
+                    interval.end,
+                    interval.reason,
+                )
+            })
+            .collect();
+        assert_eq!(
+            intervals,
+            vec![
+                (1, 0.0, 6.0, "byte_rate"),
+                (0, 1.0, 3.0, "rate"),
+                (0, 4.0, 5.0, "rate"),
+            ]
+        );
+
+        // Times far beyond the i64 bucket range end up in the last bucket without overflowing.
+        let mut simulation = Simulation::new(1, 1.0);
+        for _ in 0..20 {
+            simulation.record(1e300, 0, 1);
+        }
+        let report = simulation.report(&limits);
+        assert_eq!(report.throttling.len(), 1);
+        assert_eq!(report.end, i64::MAX as f64 + 1.0);
+    }
+
+    #[test]
+    fn test_timestamps() {
+        assert_eq!(parse_time("1714567890.5"), Some(1714567890.5));
+        assert_eq!(parse_time("2024-05-01T12:51:30Z"), Some(1714567890.0));
+        assert_eq!(
+            parse_time("2024-05-01 14:51:30.25+02:00"),
+            Some(1714567890.25)
+        );
+        assert_eq!(parse_time("1969-12-31T23:59:59Z"), Some(-1.0));
+        assert_eq!(parse_time("2024-13-01T00:00:00Z"), None);
+        assert_eq!(parse_time("yesterday"), None);
+        assert_eq!(format_time(1714567890.25), "2024-05-01T12:51:30.250Z");
+        assert_eq!(format_time(951782400.0), "2000-02-29T00:00:00Z");
+    }
+}
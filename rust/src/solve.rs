@@ -0,0 +1,329 @@
+//! Synthesizes keys `prefix + tail` whose logical partition hits a target, instead of
+//! hashing random GUIDs like `BruteForceGetFor` does.
+//!
+//! The tails are enumerated over the alphabet and only the blocks after the fixed prefix are
+//! re-hashed, so a key costs about `32767 / target width` partial hashes. Running `final_mix`
+//! backwards instead yields all 12 bytes of the last block at once: the target constrains only
+//! about 15 of their 96 bits and nothing steers the rest into the alphabet, so an attempt would
+//! succeed about once in 2^48 for hex and 2^34 for alphanumerics. Even the widest alphabet,
+//! the 102 ASCII bytes that survive upper-casing, is cheaper to search.
+
+use clap::{ArgGroup, Args};
+use std::num::Wrapping;
+use std::ops::RangeInclusive;
+use std::str::FromStr;
+
+use crate::keyspace::parse_class;
+use crate::ranges::{intersect, Constraint, LOGICAL_PARTITION_COUNT};
+use crate::{final_mix, key_bytes, mix, shift_add, KeyEncoding};
+
+const DEFAULT_MAX_ATTEMPTS: u64 = 100_000_000;
+
+/// Candidate tails a search should be able to draw from, per requested key.
+const SEARCH_SPACE_PER_KEY: f64 = (1 << 24) as f64;
+
+#[derive(Args, Debug)]
+#[clap(group(ArgGroup::new("target")
+    .required(true)
+    .multiple(true)
+    .args(["logical", "partition", "constraints"])))]
+pub struct SolveArgs {
+    /// Fixed beginning of every key
+    prefix: String,
+
+    /// Target logical partition (0..32766)
+    #[clap(short = 'l', long, conflicts_with_all = ["partition", "constraints"],
+        value_parser = clap::value_parser!(i32).range(0..LOGICAL_PARTITION_COUNT as i64))]
+    logical: Option<i32>,
+
+    /// Target partition id, together with --partition-count
+    #[clap(short = 'p', long, requires = "partition_count")]
+    partition: Option<u16>,
+
+    /// Number of partitions, together with --partition
+    #[clap(short = 'c', long, requires = "partition",
+        value_parser = clap::value_parser!(i16).range(1..))]
+    partition_count: Option<i16>,
+
+    /// Additional `COUNT:ID` constraint, e.g. `--constraint 16:7 --constraint 32:13`;
+    /// keys have to satisfy all of them at once
+    #[clap(long = "constraint")]
+    constraints: Vec<Constraint>,
+
+    /// Characters allowed in the tail: hex, alnum, digits, upper, or a class like `[0-9A-Z_]`
+    #[clap(short = 'a', long, default_value = "alnum")]
+    alphabet: Alphabet,
+
+    /// Number of tail characters (chosen automatically when omitted)
+    #[clap(long)]
+    tail_length: Option<usize>,
+
+    /// Number of keys to generate
+    #[clap(short = 'n', long, default_value_t = 1)]
+    keys: usize,
+
+    /// Give up after this many candidate tails
+    #[clap(long, default_value_t = DEFAULT_MAX_ATTEMPTS)]
+    max_attempts: u64,
+
+    #[clap(long, value_enum, default_value_t)]
+    encoding: KeyEncoding,
+}
+
+/// The bytes allowed in a tail, as they look after upper-casing.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Alphabet {
+    bytes: Vec<u8>,
+}
+
+impl Alphabet {
+    pub fn new(chars: impl IntoIterator<Item = char>) -> Result<Self, String> {
+        let mut bytes = Vec::new();
+        for ch in chars {
+            if !ch.is_ascii() {
+                return Err(format!("alphabet character {ch:?} is not ASCII"));
+            }
+            bytes.push(ch.to_ascii_uppercase() as u8);
+        }
+        bytes.sort_unstable();
+        bytes.dedup();
+        // A single character gives one tail per length, which a search cannot vary.
+        if bytes.len() < 2 {
+            return Err("the alphabet needs at least two characters".to_string());
+        }
+        Ok(Alphabet { bytes })
+    }
+}
+
+impl FromStr for Alphabet {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let class = match s {
+            "hex" => "0-9A-F",
+            "alnum" => "0-9A-Z",
+            "digits" => "0-9",
+            "upper" => "A-Z",
+            _ => s
+                .strip_prefix('[')
+                .and_then(|s| s.strip_suffix(']'))
+                .ok_or_else(|| format!("unknown alphabet {s:?}"))?,
+        };
+        Alphabet::new(parse_class(class)?)
+    }
+}
+
+#[derive(Debug)]
+pub struct Solution {
+    pub attempts: u64,
+    /// The upper-cased tails, to be appended to the prefix
+    pub tails: Vec<Vec<u8>>,
+}
+
+impl SolveArgs {
+    /// The partition counts whose range tables the run looks up.
+    pub fn partition_counts(&self) -> Vec<i16> {
+        self.constraints
+            .iter()
+            .map(|constraint| constraint.partition_count)
+            .chain(self.partition_count)
+            .collect()
+    }
+}
+
+pub fn run(args: &SolveArgs) -> Result<(), String> {
+    let target = match args.logical {
+        Some(logical) => logical..=logical,
+        None => {
+            let mut constraints = args.constraints.clone();
+            if let (Some(partition_id), Some(partition_count)) =
+                (args.partition, args.partition_count)
+            {
+                constraints.push(Constraint::new(partition_count, partition_id)?);
+            }
+            intersect(&constraints)?
+        }
+    };
+
+    let prefix = key_bytes(&args.prefix, args.encoding);
+    let solution = solve(
+        &prefix,
+        &args.alphabet,
+        &target,
+        args.tail_length,
+        args.keys,
+        args.max_attempts,
+    )?;
+
+    for tail in &solution.tails {
+        println!("{}{}", args.prefix, String::from_utf8_lossy(tail));
+    }
+    eprintln!(
+        "{} key(s) found after {} attempts",
+        solution.tails.len(),
+        solution.attempts
+    );
+    if solution.tails.len() < args.keys {
+        return Err(format!(
+            "only {} of {} keys found within {} attempts",
+            solution.tails.len(),
+            args.keys,
+            args.max_attempts
+        ));
+    }
+    Ok(())
+}
+
+/// Finds up to `keys` tails for `prefix` (already encoded and upper-cased) that land in `target`.
+pub fn solve(
+    prefix: &[u8],
+    alphabet: &Alphabet,
+    target: &RangeInclusive<i32>,
+    tail_length: Option<usize>,
+    keys: usize,
+    max_attempts: u64,
+) -> Result<Solution, String> {
+    let tail_length = tail_length.unwrap_or_else(|| {
+        let needed = SEARCH_SPACE_PER_KEY * keys as f64;
+        (1..)
+            .find(|&length| (alphabet.bytes.len() as f64).powi(length) >= needed)
+            .unwrap() as usize
+    });
+    if tail_length == 0 {
+        return Err("the tail needs at least one character".to_string());
+    }
+    Ok(solve_search(
+        prefix,
+        alphabet,
+        target,
+        tail_length,
+        keys,
+        max_attempts,
+    ))
+}
+
+type State = (Wrapping<u32>, Wrapping<u32>, Wrapping<u32>);
+
+/// lookup3 state after absorbing the 12-byte `blocks` of a key with `total_length` bytes,
+/// assuming more input follows them.
+fn absorb(total_length: usize, blocks: &[u8]) -> State {
+    let initial = Wrapping(0xdeadbeefu32) + Wrapping(total_length as u32);
+    let (mut a, mut b, mut c) = (initial, initial, initial);
+    for block in blocks.chunks_exact(12) {
+        a += shift_add(&block[..4]);
+        b += shift_add(&block[4..8]);
+        c += shift_add(&block[8..]);
+        mix(&mut a, &mut b, &mut c);
+    }
+    (a, b, c)
+}
+
+/// Finishes the hash from `state` with the remaining (non-empty) `rest` of the key.
+fn finish(state: State, rest: &[u8]) -> i32 {
+    let (mut a, mut b, mut c) = state;
+    let mut chunks = rest.chunks(12).peekable();
+    while let Some(chunk) = chunks.next() {
+        let word = |range: std::ops::Range<usize>| {
+            shift_add(
+                &chunk[usize::min(range.start, chunk.len())..usize::min(range.end, chunk.len())],
+            )
+        };
+        a += word(0..4);
+        b += word(4..8);
+        c += word(8..12);
+        if chunks.peek().is_some() {
+            mix(&mut a, &mut b, &mut c);
+        }
+    }
+    final_mix(&mut a, &mut b, &mut c);
+    ((c.0 ^ b.0) % LOGICAL_PARTITION_COUNT as u32) as i32
+}
+
+pub fn solve_search(
+    prefix: &[u8],
+    alphabet: &Alphabet,
+    target: &RangeInclusive<i32>,
+    tail_length: usize,
+    keys: usize,
+    max_attempts: u64,
+) -> Solution {
+    let total_length = prefix.len() + tail_length;
+    // Blocks that lie completely inside the prefix and are followed by more input never change.
+    let fixed = 12 * usize::min(prefix.len() / 12, (total_length - 1) / 12);
+    let state = absorb(total_length, &prefix[..fixed]);
+
+    let mut digits = vec![0usize; tail_length];
+    let mut rest = prefix[fixed..].to_vec();
+    rest.resize(rest.len() + tail_length, alphabet.bytes[0]);
+    let tail_start = rest.len() - tail_length;
+
+    let mut solution = Solution {
+        attempts: 0,
+        tails: Vec::new(),
+    };
+    loop {
+        if solution.tails.len() >= keys || solution.attempts >= max_attempts {
+            return solution;
+        }
+        solution.attempts += 1;
+        if target.contains(&finish(state, &rest)) {
+            solution.tails.push(rest[tail_start..].to_vec());
+        }
+
+        // Next tail, last character first; stop once all combinations were tried.
+        let mut position = tail_length;
+        loop {
+            if position == 0 {
+                return solution;
+            }
+            position -= 1;
+            digits[position] += 1;
+            if digits[position] < alphabet.bytes.len() {
+                rest[tail_start + position] = alphabet.bytes[digits[position]];
+                break;
+            }
+            digits[position] = 0;
+            rest[tail_start + position] = alphabet.bytes[0];
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{hash, to_logical_encoded};
+
+    fn logical(bytes: &[u8]) -> i32 {
+        let (c, b, _) = hash(bytes);
+        ((c ^ b) % LOGICAL_PARTITION_COUNT as u32) as i32
+    }
+
+    #[test]
+    fn test_search_stays_in_the_alphabet() -> Result<(), String> {
+        let hex: Alphabet = "hex".parse()?;
+        assert!("[A]".parse::<Alphabet>().is_err());
+        assert!("[aA]".parse::<Alphabet>().is_err());
+        let solution = solve(b"TENANT-", &hex, &(30000..=30000), None, 2, 10_000_000)?;
+
+        assert_eq!(solution.tails.len(), 2);
+        for tail in &solution.tails {
+            assert!(tail.iter().all(|byte| hex.bytes.contains(byte)));
+            let key = format!("tenant-{}", String::from_utf8_lossy(tail).to_lowercase());
+            assert_eq!(to_logical_encoded(&key, KeyEncoding::Utf8), 30000);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_search_rehashes_from_midstate() {
+        let digits = Alphabet::new('0'..='9').unwrap();
+        let prefix = b"A-PREFIX-LONGER-THAN-ONE-BLOCK-";
+        let solution = solve_search(prefix, &digits, &(0..=32766), 3, 5, 5);
+        let tails: Vec<&[u8]> = solution.tails.iter().map(Vec::as_slice).collect();
+        assert_eq!(tails, [b"000", b"001", b"002", b"003", b"004"]);
+
+        let target = 777..=777;
+        let solution = solve_search(prefix, &digits, &target, 6, 1, 10_000_000);
+        assert_eq!(logical(&[&prefix[..], &solution.tails[0]].concat()), 777);
+    }
+}
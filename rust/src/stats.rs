@@ -0,0 +1,139 @@
+//! Contingency-table tests for comparing key distributions over partitions.
+
+use serde::Serialize;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ChiSquare {
+    pub statistic: f64,
+    pub degrees_of_freedom: u64,
+    /// Probability of a statistic at least this large if the rows came from the same distribution
+    pub p_value: f64,
+}
+
+/// Pearson's chi-square test of homogeneity of the rows of a contingency table of counts.
+/// Columns and rows that are empty throughout do not count towards the degrees of freedom.
+pub fn chi_square(table: &[Vec<f64>]) -> ChiSquare {
+    let columns = table.first().map_or(0, Vec::len);
+    let row_totals: Vec<f64> = table.iter().map(|row| row.iter().sum()).collect();
+    let column_totals: Vec<f64> = (0..columns)
+        .map(|column| table.iter().map(|row| row[column]).sum())
+        .collect();
+    let total: f64 = row_totals.iter().sum();
+
+    let mut statistic = 0.0;
+    for (row, row_total) in table.iter().zip(&row_totals) {
+        for (observed, column_total) in row.iter().zip(&column_totals) {
+            let expected = row_total * column_total / total;
+            if expected > 0.0 {
+                statistic += (observed - expected).powi(2) / expected;
+            }
+        }
+    }
+    let used = |totals: &[f64]| totals.iter().filter(|total| **total > 0.0).count() as u64;
+    let degrees_of_freedom =
+        used(&row_totals).saturating_sub(1) * used(&column_totals).saturating_sub(1);
+    ChiSquare {
+        statistic,
+        degrees_of_freedom,
+        p_value: if degrees_of_freedom == 0 {
+            1.0
+        } else {
+            upper_gamma(degrees_of_freedom as f64 / 2.0, statistic / 2.0)
+        },
+    }
+}
+
+/// Regularized upper incomplete gamma function Q(a, x), by its series for small `x` and
+/// its continued fraction otherwise (Numerical Recipes, 6.2).
+pub fn upper_gamma(a: f64, x: f64) -> f64 {
+    if x <= 0.0 {
+        return 1.0;
+    }
+    let prefix = (-x + a * x.ln() - ln_gamma(a)).exp();
+    if x < a + 1.0 {
+        let (mut term, mut sum, mut n) = (1.0 / a, 1.0 / a, a);
+        for _ in 0..1000 {
+            n += 1.0;
+            term *= x / n;
+            sum += term;
+            if term.abs() < sum.abs() * 1e-15 {
+                break;
+            }
+        }
+        return (1.0 - sum * prefix).clamp(0.0, 1.0);
+    }
+
+    // Lentz's method for the continued fraction.
+    let tiny = 1e-300;
+    let mut b = x + 1.0 - a;
+    let mut c = 1.0 / tiny;
+    let mut d = 1.0 / b;
+    let mut fraction = d;
+    for i in 1..1000 {
+        let an = -(i as f64) * (i as f64 - a);
+        b += 2.0;
+        d = an * d + b;
+        if d.abs() < tiny {
+            d = tiny;
+        }
+        c = b + an / c;
+        if c.abs() < tiny {
+            c = tiny;
+        }
+        d = 1.0 / d;
+        let delta = d * c;
+        fraction *= delta;
+        if (delta - 1.0).abs() < 1e-15 {
+            break;
+        }
+    }
+    (prefix * fraction).clamp(0.0, 1.0)
+}
+
+/// ln Γ(x) for x > 0 (Lanczos approximation, g = 7).
+fn ln_gamma(x: f64) -> f64 {
+    const COEFFICIENTS: [f64; 9] = [
+        0.999_999_999_999_809_9,
+        676.520_368_121_885_1,
+        -1_259.139_216_722_402_8,
+        771.323_428_777_653_1,
+        -176.615_029_162_140_6,
+        12.507_343_278_686_905,
+        -0.138_571_095_265_720_12,
+        9.984_369_578_019_572e-6,
+        1.505_632_735_149_311_6e-7,
+    ];
+    if x < 0.5 {
+        let pi = std::f64::consts::PI;
+        return (pi / (pi * x).sin()).ln() - ln_gamma(1.0 - x);
+    }
+    let x = x - 1.0;
+    let mut sum = COEFFICIENTS[0];
+    for (i, coefficient) in COEFFICIENTS.iter().enumerate().skip(1) {
+        sum += coefficient / (x + i as f64);
+    }
+    let t = x + 7.5;
+    0.5 * (2.0 * std::f64::consts::PI).ln() + (x + 0.5) * t.ln() - t + sum.ln()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chi_square() {
+        // Q(k/2, x/2) at the 5% critical values of the chi-square distribution.
+        assert!((upper_gamma(0.5, 3.841_459 / 2.0) - 0.05).abs() < 1e-6);
+        assert!((upper_gamma(5.0, 18.307_04 / 2.0) - 0.05).abs() < 1e-6);
+        assert!((ln_gamma(5.0) - 24f64.ln()).abs() < 1e-12);
+
+        let same = chi_square(&[vec![10.0, 20.0, 30.0, 0.0], vec![20.0, 40.0, 60.0, 0.0]]);
+        assert_eq!(same.degrees_of_freedom, 2);
+        assert!(same.statistic.abs() < 1e-12 && same.p_value > 0.999);
+
+        let different = chi_square(&[vec![50.0, 50.0], vec![90.0, 10.0]]);
+        assert_eq!(different.degrees_of_freedom, 1);
+        assert!((different.statistic - 38.095_238).abs() < 1e-5);
+        assert!(different.p_value < 1e-8);
+    }
+}
@@ -0,0 +1,193 @@
+//! SpaceSaving summaries (Metwally et al.) for the heaviest keys of a stream in fixed memory.
+//!
+//! A summary keeps at most `capacity` counters. A new key that finds the summary full takes
+//! over the smallest counter and inherits its count as error, so every reported count is an
+//! upper bound that overestimates by at most `error`. Summaries merge by adding the counts of
+//! both sides, where a key missing from a full summary may have had up to its minimum count.
+//! Counts are summed weights, so they need not be whole numbers.
+
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::{BTreeSet, HashMap};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SpaceSaving {
+    capacity: usize,
+    counters: HashMap<String, Counter>,
+    /// Counters by `order_key` of their count; equal counts are listed heaviest first in key order
+    #[serde(skip)]
+    order: BTreeSet<(u64, Reverse<String>)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+struct Counter {
+    count: f64,
+    error: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct KeyCount {
+    pub key: String,
+    pub count: f64,
+    /// How much `count` may overestimate the true count
+    pub error: f64,
+}
+
+/// Non-negative finite counts order like the bit patterns of their IEEE 754 representation.
+fn order_key(count: f64) -> u64 {
+    count.to_bits()
+}
+
+impl SpaceSaving {
+    pub fn new(capacity: usize) -> Self {
+        SpaceSaving {
+            capacity: capacity.max(1),
+            counters: HashMap::new(),
+            order: BTreeSet::new(),
+        }
+    }
+
+    /// Adds a non-negative finite weight to the count of `key`.
+    pub fn add(&mut self, key: &str, weight: f64) {
+        if let Some(counter) = self.counters.get_mut(key) {
+            self.order
+                .remove(&(order_key(counter.count), Reverse(key.to_string())));
+            counter.count += weight;
+            self.order
+                .insert((order_key(counter.count), Reverse(key.to_string())));
+            return;
+        }
+
+        let mut counter = Counter {
+            count: weight,
+            error: 0.0,
+        };
+        if self.counters.len() >= self.capacity {
+            let (_, Reverse(min_key)) = self.order.pop_first().unwrap();
+            let min_count = self.counters.remove(&min_key).unwrap().count;
+            counter = Counter {
+                count: min_count + weight,
+                error: min_count,
+            };
+        }
+        self.order
+            .insert((order_key(counter.count), Reverse(key.to_string())));
+        self.counters.insert(key.to_string(), counter);
+    }
+
+    /// Count a key not in the summary may have had.
+    fn floor(&self) -> f64 {
+        if self.counters.len() < self.capacity {
+            0.0
+        } else {
+            self.order
+                .first()
+                .map_or(0.0, |(_, Reverse(key))| self.counters[key].count)
+        }
+    }
+
+    pub fn merge(&mut self, other: &SpaceSaving) {
+        let (own_floor, other_floor) = (self.floor(), other.floor());
+        let mut merged: HashMap<String, Counter> = HashMap::new();
+        for (key, counter) in &self.counters {
+            let theirs = other.counters.get(key).copied().unwrap_or(Counter {
+                count: other_floor,
+                error: other_floor,
+            });
+            merged.insert(
+                key.clone(),
+                Counter {
+                    count: counter.count + theirs.count,
+                    error: counter.error + theirs.error,
+                },
+            );
+        }
+        for (key, counter) in &other.counters {
+            merged.entry(key.clone()).or_insert(Counter {
+                count: counter.count + own_floor,
+                error: counter.error + own_floor,
+            });
+        }
+
+        let capacity = usize::max(self.capacity, other.capacity);
+        let mut entries: Vec<(String, Counter)> = merged.into_iter().collect();
+        entries
+            .sort_by(|(a_key, a), (b_key, b)| b.count.total_cmp(&a.count).then(a_key.cmp(b_key)));
+        entries.truncate(capacity);
+
+        *self = SpaceSaving::new(capacity);
+        for (key, counter) in entries {
+            self.order
+                .insert((order_key(counter.count), Reverse(key.clone())));
+            self.counters.insert(key, counter);
+        }
+    }
+
+    /// The `k` keys with the highest counts, heaviest first.
+    pub fn top(&self, k: usize) -> Vec<KeyCount> {
+        self.order
+            .iter()
+            .rev()
+            .take(k)
+            .map(|(_, Reverse(key))| KeyCount {
+                key: key.clone(),
+                count: self.counters[key].count,
+                error: self.counters[key].error,
+            })
+            .collect()
+    }
+
+    /// Rebuilds the ordering index, which is not serialized.
+    pub fn reindex(&mut self) {
+        self.order = self
+            .counters
+            .iter()
+            .map(|(key, counter)| (order_key(counter.count), Reverse(key.clone())))
+            .collect();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_heavy_hitters_survive_a_long_tail() {
+        let mut summary = SpaceSaving::new(10);
+        for i in 0..10_000 {
+            summary.add("hot", 1.0);
+            summary.add(&format!("cold-{i}"), 1.0);
+            if i % 4 == 0 {
+                summary.add("warm", 1.0);
+            }
+        }
+        let top = summary.top(2);
+        assert_eq!(top[0].key, "hot");
+        assert!(top[0].count >= 10_000.0 && top[0].count - top[0].error <= 10_000.0);
+        assert_eq!(top[1].key, "warm");
+        assert_eq!(summary.counters.len(), 10);
+    }
+
+    #[test]
+    fn test_merge_adds_counts() {
+        let (mut left, mut right) = (SpaceSaving::new(4), SpaceSaving::new(4));
+        left.add("a", 5.0);
+        left.add("b", 0.5);
+        right.add("a", 2.0);
+        right.add("c", 7.0);
+        left.merge(&right);
+
+        let top = left.top(3);
+        assert_eq!(
+            top.iter()
+                .map(|entry| (entry.key.as_str(), entry.count))
+                .collect::<Vec<_>>(),
+            vec![("a", 7.0), ("c", 7.0), ("b", 0.5)]
+        );
+
+        let mut restored: SpaceSaving =
+            serde_json::from_str(&serde_json::to_string(&left).unwrap()).unwrap();
+        restored.reindex();
+        assert_eq!(restored, left);
+    }
+}
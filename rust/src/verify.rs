@@ -0,0 +1,187 @@
+use clap::Args;
+use std::path::PathBuf;
+
+use crate::{get_ranges, to_logical_encoded, to_partition_id, KeyEncoding};
+
+/// The 1..32 partition vectors also checked by `MyTester.TestCompliance` on the .NET side.
+pub const SERVICE_BUS_VECTORS: &str = include_str!("../vectors/service_bus.tsv");
+
+#[derive(Args, Debug)]
+pub struct VerifyArgs {
+    /// Vector files with `key<TAB>count<TAB>expected[<TAB>encoding]` lines
+    /// (checks the bundled Service Bus vectors when omitted)
+    files: Vec<PathBuf>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Vector {
+    /// Line of the vector in its file, for reporting
+    pub line: usize,
+    pub key: String,
+    pub partition_count: i16,
+    pub expected_partition_id: u16,
+    pub encoding: KeyEncoding,
+}
+
+#[derive(Debug)]
+pub struct Mismatch<'a> {
+    pub vector: &'a Vector,
+    pub actual_partition_id: u16,
+}
+
+pub fn run(args: &VerifyArgs) -> Result<(), String> {
+    let mut sources = Vec::new();
+    if args.files.is_empty() {
+        sources.push(("<bundled>".to_string(), SERVICE_BUS_VECTORS.to_string()));
+    }
+    for path in &args.files {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| format!("cannot read {}: {e}", path.display()))?;
+        sources.push((path.display().to_string(), text));
+    }
+
+    let mut total = 0;
+    let mut failed = 0;
+    for (name, text) in &sources {
+        let vectors = parse_vectors(text).map_err(|e| format!("{name}: {e}"))?;
+        let mismatches = check_vectors(&vectors);
+        if !mismatches.is_empty() {
+            print!("{}", mismatch_report(name, &mismatches));
+        }
+        total += vectors.len();
+        failed += mismatches.len();
+    }
+
+    println!(
+        "{} vectors checked, {} passed, {} failed",
+        total,
+        total - failed,
+        failed
+    );
+    if failed > 0 {
+        return Err(format!("{failed} of {total} vectors do not match"));
+    }
+    Ok(())
+}
+
+/// Parses tab separated vector lines. Empty lines and lines starting with `#` are ignored,
+/// the encoding column defaults to `utf8`.
+pub fn parse_vectors(text: &str) -> Result<Vec<Vector>, String> {
+    let mut vectors = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        let line_number = index + 1;
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields: Vec<&str> = line.split('\t').collect();
+        if !(3..=4).contains(&fields.len()) {
+            return Err(format!(
+                "line {line_number}: expected `key<TAB>count<TAB>expected[<TAB>encoding]`"
+            ));
+        }
+        let partition_count: i16 = fields[1]
+            .trim()
+            .parse()
+            .ok()
+            .filter(|count| *count > 0)
+            .ok_or_else(|| {
+                format!(
+                    "line {line_number}: invalid partition count {:?}",
+                    fields[1]
+                )
+            })?;
+        let expected_partition_id: u16 = fields[2]
+            .trim()
+            .parse()
+            .ok()
+            .filter(|id| (*id as i32) < partition_count as i32)
+            .ok_or_else(|| format!("line {line_number}: invalid partition id {:?}", fields[2]))?;
+        let encoding = match fields.get(3) {
+            Some(encoding) => encoding
+                .trim()
+                .parse()
+                .map_err(|e| format!("line {line_number}: {e}"))?,
+            None => KeyEncoding::default(),
+        };
+        vectors.push(Vector {
+            line: line_number,
+            key: fields[0].to_string(),
+            partition_count,
+            expected_partition_id,
+            encoding,
+        });
+    }
+    Ok(vectors)
+}
+
+/// Partition of a vector under the standard table; vectors record what the broker does, so
+/// `--range-table` does not apply here.
+pub fn actual_partition_id(vector: &Vector) -> u16 {
+    to_partition_id(
+        &get_ranges(vector.partition_count),
+        to_logical_encoded(&vector.key, vector.encoding),
+    )
+}
+
+pub fn check_vectors(vectors: &[Vector]) -> Vec<Mismatch<'_>> {
+    vectors
+        .iter()
+        .filter_map(|vector| {
+            let actual_partition_id = actual_partition_id(vector);
+            (actual_partition_id != vector.expected_partition_id).then_some(Mismatch {
+                vector,
+                actual_partition_id,
+            })
+        })
+        .collect()
+}
+
+/// Renders mismatches like a unified diff: `-` is the expected line, `+` the computed one.
+pub fn mismatch_report(name: &str, mismatches: &[Mismatch]) -> String {
+    let mut report = format!("--- {name} (expected)\n+++ {name} (actual)\n");
+    for mismatch in mismatches {
+        let vector = mismatch.vector;
+        report.push_str(&format!(
+            "@@ line {} @@\n-{}\t{}\t{}\t{}\n+{}\t{}\t{}\t{}\n",
+            vector.line,
+            vector.key,
+            vector.partition_count,
+            vector.expected_partition_id,
+            vector.encoding.name(),
+            vector.key,
+            vector.partition_count,
+            mismatch.actual_partition_id,
+            vector.encoding.name(),
+        ));
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mismatches_are_reported() -> Result<(), String> {
+        let text = "# comment\n\n\
+            00000000-1313-3232-80C6-DEADDEADBEEF\t32\t13\n\
+            00000000-1313-3232-80C6-DEADDEADBEEF\t32\t12\tascii\n";
+        let vectors = parse_vectors(text)?;
+        let mismatches = check_vectors(&vectors);
+
+        assert_eq!(mismatches.len(), 1);
+        assert_eq!(mismatches[0].vector.line, 4);
+        assert_eq!(mismatches[0].actual_partition_id, 13);
+        assert!(mismatch_report("v.tsv", &mismatches)
+            .contains("-00000000-1313-3232-80C6-DEADDEADBEEF\t32\t12\tascii\n"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_lines_are_rejected() {
+        assert!(parse_vectors("key\t0\t0\n").is_err());
+        assert!(parse_vectors("key\t4\t4\n").is_err());
+        assert!(parse_vectors("key\t4\t1\tebcdic\n").is_err());
+        assert!(parse_vectors("key 4 1\n").is_err());
+    }
+}
@@ -0,0 +1,641 @@
+use clap::{Args, ValueEnum};
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::io::{BufRead, BufReader, IsTerminal, Seek, SeekFrom};
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::analyze::format_count;
+use crate::partitioner::Scheme;
+use crate::simulate::parse_time;
+use crate::topk::{KeyCount, SpaceSaving};
+
+/// How long to wait before looking for new data at the end of a followed file.
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+#[derive(Args, Debug)]
+pub struct WatchArgs {
+    /// Log file to read (reads stdin when omitted)
+    input: Option<PathBuf>,
+
+    /// Number of partitions
+    #[clap(short = 'c', long, value_parser = clap::value_parser!(i16).range(1..))]
+    partition_count: i16,
+
+    /// Partitioning scheme that places the keys
+    #[clap(long, value_enum, default_value_t)]
+    scheme: Scheme,
+
+    /// Keep reading the file as it grows, like `tail -f`. A file read without --follow needs
+    /// --time-field, since its records arrive faster than they were written
+    #[clap(short = 'f', long)]
+    follow: bool,
+
+    /// Tab-separated column (1-based) holding the record time, seconds since the epoch or
+    /// RFC 3339; windows, rates and snapshots then follow record time instead of arrival time
+    #[clap(long, value_parser = clap::value_parser!(u16).range(1..))]
+    time_field: Option<u16>,
+
+    /// Tab-separated column (1-based) holding the partition key
+    #[clap(long, default_value_t = 1, value_parser = clap::value_parser!(u16).range(1..))]
+    key_field: u16,
+
+    /// Tab-separated column (1-based) holding the message size; the line length is used otherwise
+    #[clap(long, value_parser = clap::value_parser!(u16).range(1..))]
+    size_field: Option<u16>,
+
+    /// Length of the sliding window in seconds
+    #[clap(short = 'w', long, default_value_t = 60, value_parser = clap::value_parser!(u64).range(1..))]
+    window: u64,
+
+    /// Seconds between two snapshots
+    #[clap(long, default_value_t = 5, value_parser = clap::value_parser!(u64).range(1..))]
+    interval: u64,
+
+    /// Alert when a partition gets more than this share (0..1) of the window's messages
+    #[clap(long, value_parser = parse_share)]
+    max_share: Option<f64>,
+
+    /// Alert when a partition receives more messages per second
+    #[clap(long)]
+    max_rate: Option<f64>,
+
+    /// Alert when a partition receives more bytes per second
+    #[clap(long)]
+    max_byte_rate: Option<f64>,
+
+    /// Messages the window needs before share alerts are raised
+    #[clap(long, default_value_t = 100)]
+    min_messages: u64,
+
+    /// Report the heaviest keys of every partition in the window
+    #[clap(long, default_value_t = 0)]
+    top: usize,
+
+    /// Counters kept per partition and second for the heavy-hitter summaries
+    #[clap(long, default_value_t = 64, value_parser = clap::value_parser!(u64).range(1..))]
+    top_capacity: u64,
+
+    #[clap(short = 'o', long, value_enum, default_value_t)]
+    output: WatchOutput,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum WatchOutput {
+    /// A terminal table that is redrawn with every snapshot
+    #[default]
+    Table,
+    /// One JSON event per line
+    Ndjson,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Thresholds {
+    pub max_share: Option<f64>,
+    pub max_rate: Option<f64>,
+    pub max_byte_rate: Option<f64>,
+    pub min_messages: u64,
+}
+
+/// Per-partition counts of the last `seconds` seconds, kept in one bucket per second.
+#[derive(Debug)]
+pub struct Window {
+    seconds: u64,
+    partition_count: usize,
+    /// Heaviest keys reported per partition, 0 to not track keys at all
+    top: usize,
+    top_capacity: usize,
+    buckets: VecDeque<Bucket>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    second: u64,
+    messages: Vec<u64>,
+    bytes: Vec<u64>,
+    top_keys: Vec<Option<SpaceSaving>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PartitionLoad {
+    pub partition_id: u16,
+    pub messages: u64,
+    pub bytes: u64,
+    pub share: f64,
+    pub rate: f64,
+    pub byte_rate: f64,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub top_keys: Vec<KeyCount>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Snapshot {
+    pub time: u64,
+    pub window_seconds: u64,
+    pub messages: u64,
+    pub skipped_lines: u64,
+    pub partitions: Vec<PartitionLoad>,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+pub struct Alert {
+    pub time: u64,
+    pub partition_id: u16,
+    pub reason: &'static str,
+    pub value: f64,
+    pub threshold: f64,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+enum Event<'a> {
+    Snapshot(&'a Snapshot),
+    Alert(&'a Alert),
+}
+
+impl Window {
+    pub fn new(seconds: u64, partition_count: usize) -> Self {
+        Window {
+            seconds,
+            partition_count,
+            top: 0,
+            top_capacity: 0,
+            buckets: VecDeque::new(),
+        }
+    }
+
+    /// Also keeps a heavy-hitter summary per partition and second, so snapshots can
+    /// report the `top` heaviest keys of every partition.
+    pub fn with_top_keys(mut self, top: usize, top_capacity: usize) -> Self {
+        self.top = top;
+        self.top_capacity = top_capacity;
+        self
+    }
+
+    pub fn record(&mut self, second: u64, partition_id: u16, key: &str, bytes: u64) {
+        self.expire(second);
+        if self
+            .buckets
+            .back()
+            .is_none_or(|bucket| bucket.second != second)
+        {
+            self.buckets.push_back(Bucket {
+                second,
+                messages: vec![0; self.partition_count],
+                bytes: vec![0; self.partition_count],
+                top_keys: vec![None; self.partition_count],
+            });
+        }
+        let bucket = self.buckets.back_mut().unwrap();
+        bucket.messages[partition_id as usize] += 1;
+        bucket.bytes[partition_id as usize] += bytes;
+        if self.top > 0 {
+            bucket.top_keys[partition_id as usize]
+                .get_or_insert_with(|| SpaceSaving::new(self.top_capacity))
+                .add(key, 1.0);
+        }
+    }
+
+    fn expire(&mut self, second: u64) {
+        while self
+            .buckets
+            .front()
+            .is_some_and(|bucket| bucket.second + self.seconds <= second)
+        {
+            self.buckets.pop_front();
+        }
+    }
+
+    /// Sums the buckets of the window ending at `second`; rates are per second of the
+    /// window, or of the time watched so far while the window is still filling up.
+    pub fn snapshot(&mut self, second: u64, time: u64, skipped_lines: u64) -> Snapshot {
+        self.expire(second);
+        let mut messages = vec![0u64; self.partition_count];
+        let mut bytes = vec![0u64; self.partition_count];
+        let mut top_keys = vec![SpaceSaving::new(self.top_capacity); self.partition_count];
+        for bucket in &self.buckets {
+            for partition in 0..self.partition_count {
+                messages[partition] += bucket.messages[partition];
+                bytes[partition] += bucket.bytes[partition];
+                if let Some(summary) = &bucket.top_keys[partition] {
+                    top_keys[partition].merge(summary);
+                }
+            }
+        }
+        let total: u64 = messages.iter().sum();
+        let elapsed = u64::min(self.seconds, second + 1) as f64;
+        Snapshot {
+            time,
+            window_seconds: self.seconds,
+            messages: total,
+            skipped_lines,
+            partitions: (0..self.partition_count)
+                .map(|partition| PartitionLoad {
+                    partition_id: partition as u16,
+                    messages: messages[partition],
+                    bytes: bytes[partition],
+                    share: if total == 0 {
+                        0.0
+                    } else {
+                        messages[partition] as f64 / total as f64
+                    },
+                    rate: messages[partition] as f64 / elapsed,
+                    byte_rate: bytes[partition] as f64 / elapsed,
+                    top_keys: top_keys[partition].top(self.top),
+                })
+                .collect(),
+        }
+    }
+}
+
+pub fn alerts(snapshot: &Snapshot, thresholds: &Thresholds) -> Vec<Alert> {
+    let mut alerts = Vec::new();
+    for load in &snapshot.partitions {
+        let mut check = |reason, value: f64, threshold: Option<f64>| {
+            if let Some(threshold) = threshold.filter(|threshold| value > *threshold) {
+                alerts.push(Alert {
+                    time: snapshot.time,
+                    partition_id: load.partition_id,
+                    reason,
+                    value,
+                    threshold,
+                });
+            }
+        };
+        if snapshot.messages >= thresholds.min_messages {
+            check("share", load.share, thresholds.max_share);
+        }
+        check("rate", load.rate, thresholds.max_rate);
+        check("byte_rate", load.byte_rate, thresholds.max_byte_rate);
+    }
+    alerts
+}
+
+fn parse_share(text: &str) -> Result<f64, String> {
+    text.parse()
+        .ok()
+        .filter(|share| (0.0..=1.0).contains(share))
+        .ok_or_else(|| format!("{text:?} is not a share between 0 and 1"))
+}
+
+/// Where the seconds of the window come from.
+enum Clock {
+    /// Arrival time since the watch started
+    Arrival(Instant),
+    /// Record time: the first record's time and the newest second since then
+    Record {
+        time_field: u16,
+        first: Option<f64>,
+        latest: u64,
+    },
+}
+
+impl Clock {
+    /// Second of the window for a record; `None` when its time is missing. Records older than
+    /// the newest one count towards the newest second, so the window only moves forward.
+    fn second_of(&mut self, line: &str) -> Option<u64> {
+        match self {
+            Clock::Arrival(started) => Some(started.elapsed().as_secs()),
+            Clock::Record {
+                time_field,
+                first,
+                latest,
+            } => {
+                let time = parse_time(line.split('\t').nth(*time_field as usize - 1)?)?;
+                let first = *first.get_or_insert(time);
+                *latest = u64::max(*latest, (time - first).max(0.0) as u64);
+                Some(*latest)
+            }
+        }
+    }
+
+    /// The current second of the window and the Unix time it stands for.
+    fn now(&self) -> (u64, u64) {
+        match self {
+            Clock::Arrival(started) => (
+                started.elapsed().as_secs(),
+                SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map_or(0, |duration| duration.as_secs()),
+            ),
+            Clock::Record { first, latest, .. } => (
+                *latest,
+                first.map_or(0.0, |first| first + *latest as f64).max(0.0) as u64,
+            ),
+        }
+    }
+}
+
+pub fn run(args: &WatchArgs) -> Result<(), String> {
+    args.scheme.check_partition_count(args.partition_count)?;
+    if args.input.is_some() && !args.follow && args.time_field.is_none() {
+        return Err(
+            "a file read without --follow is replayed faster than it was written, \
+             so its rates need record times: pass --time-field or --follow"
+                .to_string(),
+        );
+    }
+    let lines = spawn_reader(args.input.clone(), args.follow)?;
+    let partitioner = args.scheme.partitioner(args.partition_count);
+    let thresholds = Thresholds {
+        max_share: args.max_share,
+        max_rate: args.max_rate,
+        max_byte_rate: args.max_byte_rate,
+        min_messages: args.min_messages,
+    };
+    let mut window = Window::new(args.window, partitioner.partition_count() as usize)
+        .with_top_keys(args.top, args.top_capacity as usize);
+    let mut skipped_lines = 0;
+
+    let started = Instant::now();
+    let mut clock = match args.time_field {
+        Some(time_field) => Clock::Record {
+            time_field,
+            first: None,
+            latest: 0,
+        },
+        None => Clock::Arrival(started),
+    };
+    let interval = Duration::from_secs(args.interval);
+    let mut next_snapshot = started + interval;
+    let mut next_record_snapshot = args.interval;
+    loop {
+        let received = match clock {
+            // Record time only moves with the records, so there is nothing to report while idle.
+            Clock::Record { .. } => lines.recv().map_err(|_| RecvTimeoutError::Disconnected),
+            Clock::Arrival(_) => {
+                lines.recv_timeout(next_snapshot.saturating_duration_since(Instant::now()))
+            }
+        };
+        let finished = match received {
+            Ok(line) => {
+                let line = line?;
+                match parse_record(&line, args.key_field, args.size_field)
+                    .and_then(|(key, bytes)| Some((key, bytes, clock.second_of(&line)?)))
+                {
+                    Some((key, bytes, second)) => {
+                        let partition_id = partitioner.partition_of(key);
+                        window.record(second, partition_id, key, bytes);
+                    }
+                    None => skipped_lines += 1,
+                }
+                false
+            }
+            Err(RecvTimeoutError::Timeout) => false,
+            Err(RecvTimeoutError::Disconnected) => true,
+        };
+
+        let (second, time) = clock.now();
+        let due = match clock {
+            Clock::Arrival(_) => Instant::now() >= next_snapshot,
+            Clock::Record { .. } => second >= next_record_snapshot,
+        };
+        if finished || due {
+            let snapshot = window.snapshot(second, time, skipped_lines);
+            let alerts = alerts(&snapshot, &thresholds);
+            match args.output {
+                WatchOutput::Table => print_table(&snapshot, &alerts),
+                WatchOutput::Ndjson => {
+                    print_event(&Event::Snapshot(&snapshot))?;
+                    for alert in &alerts {
+                        print_event(&Event::Alert(alert))?;
+                    }
+                }
+            }
+            next_snapshot += interval;
+            next_record_snapshot = (second / args.interval + 1) * args.interval;
+        }
+        if finished {
+            return Ok(());
+        }
+    }
+}
+
+/// Extracts the key and the size of a tab-separated record, `None` for lines that do not have them.
+fn parse_record(line: &str, key_field: u16, size_field: Option<u16>) -> Option<(&str, u64)> {
+    let mut fields = line.split('\t');
+    let key = fields.clone().nth(key_field as usize - 1)?;
+    let bytes = match size_field {
+        Some(size_field) => fields.nth(size_field as usize - 1)?.trim().parse().ok()?,
+        None => line.len() as u64,
+    };
+    Some((key, bytes))
+}
+
+fn print_event(event: &Event) -> Result<(), String> {
+    let json = serde_json::to_string(event).map_err(|e| e.to_string())?;
+    println!("{json}");
+    Ok(())
+}
+
+fn print_table(snapshot: &Snapshot, alerts: &[Alert]) {
+    // Clear the screen and move the cursor home, so the table refreshes in place; only on a
+    // terminal, so redirected output stays free of escape codes.
+    if std::io::stdout().is_terminal() {
+        print!("\x1b[2J\x1b[H");
+    }
+    println!(
+        "{} messages in the last {}s, {} lines skipped",
+        snapshot.messages, snapshot.window_seconds, snapshot.skipped_lines
+    );
+    println!(
+        "{:>12} {:>10} {:>12} {:>7} {:>10} {:>12}",
+        "partition_id", "messages", "bytes", "share", "msg/s", "bytes/s"
+    );
+    for load in &snapshot.partitions {
+        let hot = alerts
+            .iter()
+            .any(|alert| alert.partition_id == load.partition_id);
+        println!(
+            "{:>12} {:>10} {:>12} {:>6.2}% {:>10.1} {:>12.1}{}",
+            load.partition_id,
+            load.messages,
+            load.bytes,
+            100.0 * load.share,
+            load.rate,
+            load.byte_rate,
+            if hot { "  <-- hot" } else { "" }
+        );
+        if !load.top_keys.is_empty() {
+            let top_keys: Vec<String> = load
+                .top_keys
+                .iter()
+                .map(|entry| format!("{} ({})", entry.key, format_count(entry)))
+                .collect();
+            println!("{:>12} top keys: {}", "", top_keys.join(", "));
+        }
+    }
+    for alert in alerts {
+        println!(
+            "ALERT partition {}: {} {:.3} exceeds {:.3}",
+            alert.partition_id, alert.reason, alert.value, alert.threshold
+        );
+    }
+}
+
+/// Reads lines on a separate thread, so snapshots keep coming while the input is idle.
+fn spawn_reader(
+    input: Option<PathBuf>,
+    follow: bool,
+) -> Result<Receiver<Result<String, String>>, String> {
+    let (sender, receiver) = mpsc::channel();
+    let file = match &input {
+        Some(path) => Some(
+            std::fs::File::open(path)
+                .map_err(|e| format!("cannot open {}: {e}", path.display()))?,
+        ),
+        None => None,
+    };
+
+    std::thread::spawn(move || {
+        let result = match (file, input) {
+            (Some(file), Some(path)) => {
+                follow_file(file, &path, follow, |line| sender.send(Ok(line)).is_ok())
+            }
+            _ => {
+                let mut result = Ok(());
+                for line in std::io::stdin().lock().lines() {
+                    let line = match line {
+                        Ok(line) => line,
+                        Err(e) => {
+                            result = Err(e.to_string());
+                            break;
+                        }
+                    };
+                    if sender.send(Ok(line)).is_err() {
+                        break;
+                    }
+                }
+                result
+            }
+        };
+        if let Err(message) = result {
+            let _ = sender.send(Err(message));
+        }
+    });
+    Ok(receiver)
+}
+
+/// Hands complete lines to `emit` until it returns `false` or the file ends; with `follow`
+/// it waits for more data instead and starts over when the file gets truncated.
+fn follow_file(
+    file: std::fs::File,
+    path: &std::path::Path,
+    follow: bool,
+    mut emit: impl FnMut(String) -> bool,
+) -> Result<(), String> {
+    let mut reader = BufReader::new(file);
+    let mut position = 0u64;
+    let mut pending = String::new();
+    loop {
+        let read = reader
+            .read_line(&mut pending)
+            .map_err(|e| format!("cannot read {}: {e}", path.display()))?;
+        position += read as u64;
+        if pending.ends_with('\n') {
+            let line = pending.trim_end_matches(['\r', '\n']).to_string();
+            pending.clear();
+            if !emit(line) {
+                return Ok(());
+            }
+            continue;
+        }
+        if read > 0 {
+            // A partial line; the writer has not finished it yet.
+            continue;
+        }
+        if !follow {
+            if !pending.is_empty() {
+                emit(std::mem::take(&mut pending));
+            }
+            return Ok(());
+        }
+        std::thread::sleep(POLL_INTERVAL);
+        let length = std::fs::metadata(path).map_or(position, |metadata| metadata.len());
+        if length < position {
+            reader
+                .seek(SeekFrom::Start(0))
+                .map_err(|e| format!("cannot read {}: {e}", path.display()))?;
+            position = 0;
+            pending.clear();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_window_slides_and_alerts() {
+        let mut window = Window::new(10, 4);
+        for second in 0..10 {
+            window.record(second, 1, "a", 100);
+            window.record(second, 2, "b", 50);
+        }
+        window.record(9, 1, "c", 100);
+
+        let snapshot = window.snapshot(9, 0, 0);
+        assert_eq!(snapshot.messages, 21);
+        assert_eq!(snapshot.partitions[1].messages, 11);
+        assert_eq!(snapshot.partitions[1].bytes, 1100);
+        assert!((snapshot.partitions[1].rate - 1.1).abs() < 1e-9);
+
+        let thresholds = Thresholds {
+            max_share: Some(0.5),
+            max_rate: Some(2.0),
+            max_byte_rate: None,
+            min_messages: 10,
+        };
+        let alerts = alerts(&snapshot, &thresholds);
+        assert_eq!(alerts.len(), 1);
+        assert_eq!((alerts[0].partition_id, alerts[0].reason), (1, "share"));
+
+        // Five seconds later the first five buckets have left the window.
+        let snapshot = window.snapshot(14, 0, 0);
+        assert_eq!(snapshot.partitions[2].messages, 5);
+    }
+
+    #[test]
+    fn test_window_reports_top_keys() {
+        let mut window = Window::new(2, 2).with_top_keys(1, 4);
+        window.record(0, 0, "old", 1);
+        for second in 1..3 {
+            window.record(second, 0, "hot", 1);
+            window.record(second, 0, "hot", 1);
+            window.record(second, 0, "cold", 1);
+        }
+
+        let snapshot = window.snapshot(2, 0, 0);
+        let top = &snapshot.partitions[0].top_keys;
+        assert_eq!(top.len(), 1);
+        assert_eq!((top[0].key.as_str(), top[0].count), ("hot", 4.0));
+        assert!(snapshot.partitions[1].top_keys.is_empty());
+    }
+
+    #[test]
+    fn test_records_are_split_into_fields() {
+        assert_eq!(parse_record("k1", 1, None), Some(("k1", 2)));
+        assert_eq!(parse_record("t\tk1\t42", 2, Some(3)), Some(("k1", 42)));
+        assert_eq!(parse_record("t\tk1", 2, Some(3)), None);
+
+        let mut clock = Clock::Record {
+            time_field: 1,
+            first: None,
+            latest: 0,
+        };
+        let seconds: Vec<Option<u64>> = [
+            "1714567890\tk",
+            "2024-05-01T12:51:42Z\tk",
+            "1714567895",
+            "x",
+        ]
+        .iter()
+        .map(|line| clock.second_of(line))
+        .collect();
+        assert_eq!(seconds, [Some(0), Some(12), Some(12), None]);
+        assert_eq!(clock.now(), (12, 1714567902));
+        assert!(parse_share("0.2").is_ok() && parse_share("20").is_err());
+    }
+}